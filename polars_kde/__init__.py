@@ -1,4 +1,6 @@
 from __future__ import annotations
+from collections.abc import Mapping
+from datetime import date, datetime, time, timedelta, timezone
 from typing import TYPE_CHECKING
 
 import polars as pl
@@ -9,67 +11,1199 @@ from polars.plugins import register_plugin_function
 
 LIB = Path(__file__).parent
 
+_EPOCH = datetime(1970, 1, 1, tzinfo=timezone.utc)
+
 if TYPE_CHECKING:
-    from polars_kde.typing import IntoExprColumn
+    from collections.abc import Sequence
+
+    from polars_kde.typing import (
+        BandwidthMatrix,
+        BandwidthRule,
+        BoundaryCorrection,
+        Estimate,
+        GridOutput,
+        GridRange,
+        IntoExprColumn,
+        KdeOutput,
+        Kernel,
+        Method,
+        MultivariateBandwidthRule,
+        MultivariatePoint,
+        NanPolicy,
+        NullPolicy,
+        TemporalValue,
+    )
+
+
+def _to_float(value: TemporalValue) -> float:
+    """Converts temporal values to nanoseconds, which is the unit of temporal columns.
+
+    Dates and datetimes are measured since the Unix epoch (naive datetimes are taken
+    as UTC), times since midnight. Plain numbers are returned unchanged.
+    """
+    if isinstance(value, timedelta):
+        return float(value // timedelta(microseconds=1) * 1_000)
+    if isinstance(value, datetime):
+        if value.tzinfo is None:
+            value = value.replace(tzinfo=timezone.utc)
+        return _to_float(value - _EPOCH)
+    if isinstance(value, date):
+        return _to_float(datetime(value.year, value.month, value.day))
+    if isinstance(value, time):
+        return _to_float(
+            timedelta(
+                hours=value.hour,
+                minutes=value.minute,
+                seconds=value.second,
+                microseconds=value.microsecond,
+            )
+        )
+    return float(value)
+
+
+def _is_temporal(name: str, values: Sequence[TemporalValue]) -> bool:
+    """Returns whether the values of the argument `name` are temporal, i.e. dates,
+    datetimes, times or timedeltas, rather than plain numbers.
+
+    Temporal columns are measured in nanoseconds, so they only accept temporal values,
+    while numeric columns only accept plain numbers. This is checked against the type
+    of the column when the expression is evaluated.
+    """
+    kinds = {
+        isinstance(value, (date, time, timedelta))
+        for value in values
+        if value is not None
+    }
+    if len(kinds) > 1:
+        raise TypeError(f"`{name}` mixes temporal values and plain numbers")
+    return True in kinds
+
+
+def _kde_options(
+    estimate: Estimate,
+    kernel: Kernel,
+    bandwidth: BandwidthRule | float | timedelta,
+    bw_adjust: float,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None,
+    lower: TemporalValue | None,
+    upper: TemporalValue | None,
+    boundary: BoundaryCorrection,
+    period: float | timedelta | None,
+    method: Method,
+    rtol: float,
+    atol: float,
+    null_policy: NullPolicy,
+    nan_policy: NanPolicy,
+) -> dict:
+    """Collects the estimator options shared by all KDE functions."""
+    values = {
+        "bandwidth": None if isinstance(bandwidth, str) else [bandwidth],
+        "cv_bounds": cv_bounds,
+        "lower": None if lower is None else [lower],
+        "upper": None if upper is None else [upper],
+        "period": None if period is None else [period],
+    }
+    return {
+        "estimate": estimate,
+        "kernel": kernel,
+        "bandwidth": bandwidth if isinstance(bandwidth, str) else _to_float(bandwidth),
+        "bw_adjust": float(bw_adjust),
+        "cv_bounds": None if cv_bounds is None else tuple(map(_to_float, cv_bounds)),
+        "lower": None if lower is None else _to_float(lower),
+        "upper": None if upper is None else _to_float(upper),
+        "boundary": boundary,
+        "period": None if period is None else _to_float(period),
+        "method": method,
+        "rtol": float(rtol),
+        "atol": float(atol),
+        "null_policy": null_policy,
+        "nan_policy": nan_policy,
+        "temporal": {
+            name: _is_temporal(name, v) for name, v in values.items() if v is not None
+        },
+    }
+
+
+def _default_output(
+    eval_points: list[TemporalValue | None] | None, output: KdeOutput | None
+) -> KdeOutput:
+    """Returns the output layout, which defaults to "points" on a generated grid and
+    "density" otherwise."""
+    if output is None:
+        return "points" if eval_points is None else "density"
+    return output
+
 
+def _eval_options(
+    eval_points: list[TemporalValue | None] | None,
+    n_points: int,
+    cut: float,
+    grid: GridRange,
+    output: KdeOutput | None,
+) -> dict:
+    """Collects the evaluation points, or the options to generate them, and the output
+    layout, see `_default_output`."""
+    output = _default_output(eval_points, output)
+    return {
+        "eval_points": None
+        if eval_points is None
+        else [None if x is None else _to_float(x) for x in eval_points],
+        "temporal_eval_points": eval_points is not None
+        and _is_temporal("eval_points", eval_points),
+        "n_points": n_points,
+        "cut": float(cut),
+        "grid": grid,
+        "output": output,
+    }
 
-def kde(expr: IntoExprColumn, *, eval_points: list[float]) -> pl.Expr:
+
+def _temporal_dimensions(eval_points: Sequence[MultivariatePoint]) -> dict[str, bool]:
+    """Returns whether the coordinates of each dimension of multivariate evaluation
+    points are temporal, keyed by field name or by the index of the coordinate."""
+    dimensions: dict[str, list[TemporalValue]] = {}
+    for p in eval_points:
+        for key, value in p.items() if isinstance(p, Mapping) else enumerate(p):
+            dimensions.setdefault(str(key), []).append(value)
+    return {
+        key: _is_temporal(f"eval_points[{key}]", values)
+        for key, values in dimensions.items()
+    }
+
+
+def _multivariate_options(
+    bandwidth: MultivariateBandwidthRule | float,
+    bw_adjust: float,
+    bandwidth_matrix: BandwidthMatrix,
+    null_policy: NullPolicy,
+    nan_policy: NanPolicy,
+) -> dict:
+    """Collects the estimator options shared by the multivariate KDE functions."""
+    return {
+        "bandwidth": bandwidth if isinstance(bandwidth, str) else float(bandwidth),
+        "bw_adjust": float(bw_adjust),
+        "bandwidth_matrix": bandwidth_matrix,
+        "null_policy": null_policy,
+        "nan_policy": nan_policy,
+    }
+
+
+def kde(
+    expr: IntoExprColumn,
+    *,
+    eval_points: list[TemporalValue | None] | None = None,
+    n_points: int = 200,
+    cut: float = 3.0,
+    grid: GridRange = "local",
+    output: KdeOutput | None = None,
+    log: bool = False,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
     """Kernel Density Estimation (KDE) aggregation.
 
     Args:
-        expr (IntoExprColumn): Which column to aggregate into a population.
-        eval_points (list[float]): At which points to evaluate the KDE.
+        expr (IntoExprColumn): Which numeric column to aggregate into a population.
+        eval_points (list[TemporalValue | None] | None): At which points to evaluate
+            the KDE. For temporal columns, these may be dates, datetimes, times or
+            timedeltas. `None` is a null evaluation point, see `null_policy`.
+            If not given, the KDE is evaluated on a generated grid instead.
+        n_points (int): The number of points of a generated grid.
+        cut (float): How far a generated grid extends beyond the sample range, in
+            bandwidths.
+        grid (GridRange): "local" spans each group's own sample range, "global" one
+            range spanning all rows (only supported by `kde_static_evals`).
+        output (KdeOutput | None): "density" and "array" return the densities only, as
+            a fixed-width `Array` of the number of evaluation points, which converts
+            directly to a 2D numpy array. "points" returns a list of structs of each
+            evaluation point `x` and its `density`, "columns" a struct of the list of
+            evaluation points `x` and the list of densities. Defaults to "points" on a
+            generated grid and "density" otherwise.
+        log (bool): Whether to return the natural logarithm of the density, computed
+            with a log-sum-exp over the kernel contributions, so that it stays finite
+            far from the samples for kernels with unbounded support. The struct field
+            of the estimates is then named `log_density`.
+        weights (IntoExprColumn | None): Optional non-negative numeric column with
+            the weight of each sample. Bandwidth rules use the effective sample size.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): Either a rule of thumb
+            ("silverman" or "scott"), a plug-in selector ("sj" or "isj"), a
+            cross-validation selector ("likelihood_cv" or "least_squares_cv") or a
+            fixed positive bandwidth (a timedelta for temporal columns).
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors. Defaults to a range around
+            Silverman's bandwidth.
+        lower (TemporalValue | None): Lower bound of the support, e.g. 0 for
+            non-negative quantities. Samples must not lie below it. The density is
+            zero below the bound and corrected near it, so that it integrates to one
+            on the support.
+        upper (TemporalValue | None): Upper bound of the support, see `lower`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds.
+            "reflection" mirrors the samples at the bounds, which flattens the
+            density towards them. "renormalization" rescales each kernel by its mass
+            within the support, which keeps the slope of the density.
+        period (float | timedelta | None): The period of a periodic support, e.g. 24 for
+            the hour of the day, 2π for angles or a timedelta of one day for the
+            time of day of temporal columns. The kernel contributions wrap around the
+            period, so that the density is continuous across the wrap point and
+            integrates to one over a period. Generated grids span one period starting
+            at zero. Cannot be combined with `lower` or `upper`.
+        method (Method): "exact" sums the contributions of all samples at every
+            evaluation point. "fft" bins the samples onto a regular grid of at least
+            4096 points and at least 32 points per bandwidth and convolves them with
+            the kernel by FFT, which is much faster for large groups at the cost of a
+            small approximation error. Groups whose range spans more than about 8000
+            bandwidths, e.g. because of outliers, would need a grid of more than 2^18
+            points and are evaluated exactly instead. "tree" sorts the
+            samples and neglects distant samples as long as the error stays within
+            `atol + rtol * density`, which is exact for zero tolerances and much faster
+            for compact kernels or when evaluating at many points.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled. "drop" ignores null samples,
+            "propagate" returns null if a group contains null samples and "error"
+            raises. Null evaluation points and null rows always yield nulls unless
+            the policy is "error".
+        nan_policy (NanPolicy): How NaN and infinite values are handled. "drop"
+            ignores non-finite samples, "null" returns null if a group contains
+            non-finite samples and "error" raises. Non-finite evaluation points yield
+            null densities unless the policy is "error".
 
     Returns:
-        pl.Expr: The KDE evaluated at the given points.
+        pl.Expr: The KDE evaluated at the given points, as Float32 for Float32 input and
+            Float64 otherwise. For temporal columns the density is per nanosecond. The
+            evaluation points are returned next to the densities depending on `output`.
     """
+    args = [expr] if weights is None else [expr, weights]
     return register_plugin_function(
-        args=[expr],
+        args=args,
         plugin_path=LIB,
         function_name="kde_agg",
         is_elementwise=False,
-        returns_scalar=False,
-        kwargs={"eval_points": eval_points},
+        returns_scalar=_default_output(eval_points, output) != "points",
+        kwargs={
+            **_eval_options(
+                eval_points=eval_points,
+                n_points=n_points,
+                cut=cut,
+                grid=grid,
+                output=output,
+            ),
+            **_kde_options(
+                estimate="log_density" if log else "density",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
     )
 
 
-def kde_static_evals(expr: IntoExprColumn, *, eval_points: list[float]) -> pl.Expr:
+def kde_static_evals(
+    expr: IntoExprColumn,
+    *,
+    eval_points: list[TemporalValue | None] | None = None,
+    n_points: int = 200,
+    cut: float = 3.0,
+    grid: GridRange = "local",
+    output: KdeOutput | None = None,
+    log: bool = False,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
     """
     Kernel Density Estimation (KDE) evaluation on already aggregated data.
     Takes a column of lists of floats and evaluates the KDE at the given points.
 
     Args:
-        expr (IntoExprColumn): Column of lists of floats (pl.List(pl.Float32)).
-        eval_points (list[float]): At which points to evaluate the KDE.
+        expr (IntoExprColumn): Column of lists of numeric values, e.g.
+            pl.List(pl.Float32).
+        eval_points (list[TemporalValue | None] | None): At which points to evaluate
+            the KDE. For temporal columns, these may be dates, datetimes, times or
+            timedeltas. `None` is a null evaluation point, see `null_policy`.
+            If not given, the KDE is evaluated on a generated grid instead.
+        n_points (int): The number of points of a generated grid.
+        cut (float): How far a generated grid extends beyond the sample range, in
+            bandwidths.
+        grid (GridRange): "local" spans each group's own sample range, "global" one
+            range spanning all rows (only supported by `kde_static_evals`).
+        output (KdeOutput | None): "density" and "array" return the densities only, as
+            a fixed-width `Array` of the number of evaluation points, which converts
+            directly to a 2D numpy array. "points" returns a list of structs of each
+            evaluation point `x` and its `density`, "columns" a struct of the list of
+            evaluation points `x` and the list of densities. Defaults to "points" on a
+            generated grid and "density" otherwise.
+        log (bool): Whether to return the natural logarithm of the density, computed
+            with a log-sum-exp over the kernel contributions, so that it stays finite
+            far from the samples for kernels with unbounded support. The struct field
+            of the estimates is then named `log_density`.
+        weights (IntoExprColumn | None): Optional column of lists of non-negative
+            numeric weights, one per sample. Bandwidth rules use the effective sample
+            size.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): Either a rule of thumb
+            ("silverman" or "scott"), a plug-in selector ("sj" or "isj"), a
+            cross-validation selector ("likelihood_cv" or "least_squares_cv") or a
+            fixed positive bandwidth (a timedelta for temporal columns).
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors. Defaults to a range around
+            Silverman's bandwidth.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        method (Method): "exact" sums the contributions of all samples at every
+            evaluation point. "fft" bins the samples onto a regular grid of at least
+            4096 points and at least 32 points per bandwidth and convolves them with
+            the kernel by FFT, which is much faster for large groups at the cost of a
+            small approximation error. Groups whose range spans more than about 8000
+            bandwidths, e.g. because of outliers, would need a grid of more than 2^18
+            points and are evaluated exactly instead. "tree" sorts the
+            samples and neglects distant samples as long as the error stays within
+            `atol + rtol * density`, which is exact for zero tolerances and much faster
+            for compact kernels or when evaluating at many points.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled. "drop" ignores null samples,
+            "propagate" returns null if a group contains null samples and "error"
+            raises. Null evaluation points and null rows always yield nulls unless
+            the policy is "error".
+        nan_policy (NanPolicy): How NaN and infinite values are handled. "drop"
+            ignores non-finite samples, "null" returns null if a group contains
+            non-finite samples and "error" raises. Non-finite evaluation points yield
+            null densities unless the policy is "error".
 
     Returns:
-        pl.Expr: The KDE evaluated at the given points.
+        pl.Expr: The KDE evaluated at the given points, or on a generated grid. The
+            evaluation points are returned next to the densities depending on `output`.
     """
+    args = [expr] if weights is None else [expr, weights]
     return register_plugin_function(
-        args=[expr],
+        args=args,
         plugin_path=LIB,
         function_name="kde_static_evals",
         is_elementwise=True,
-        kwargs={"eval_points": eval_points},
+        kwargs={
+            **_eval_options(
+                eval_points=eval_points,
+                n_points=n_points,
+                cut=cut,
+                grid=grid,
+                output=output,
+            ),
+            **_kde_options(
+                estimate="log_density" if log else "density",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
     )
 
 
-def kde_dynamic_evals(expr: IntoExprColumn, eval_points: IntoExprColumn) -> pl.Expr:
+def kde_dynamic_evals(
+    expr: IntoExprColumn,
+    eval_points: IntoExprColumn,
+    *,
+    output: KdeOutput = "density",
+    log: bool = False,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
     """
     Kernel Density Estimation (KDE) evaluation on already aggregated data but with dynamic eval points.
     Takes a column of lists of floats and evaluates the KDE at the given points (which are defined row-wise)
     and can be different for each row.
 
     Args:
-        expr (IntoExprColumn): Column of lists of floats (pl.List(pl.Float32)).
-        eval_points (IntoExprColumn): Column of lists of floats (pl.List(pl.Float32)).
+        expr (IntoExprColumn): Column of lists of numeric values, e.g.
+            pl.List(pl.Float32).
+        eval_points (IntoExprColumn): Column of lists of numeric values, e.g.
+            pl.List(pl.Float32).
+        output (KdeOutput): "density" returns the densities only, "points" a list of
+            structs of each evaluation point `x` and its `density`, "columns" a struct
+            of the list of evaluation points `x` and the list of densities. "array" is
+            not supported, since the rows may have different numbers of evaluation
+            points.
+        log (bool): Whether to return the natural logarithm of the density, computed
+            with a log-sum-exp over the kernel contributions, so that it stays finite
+            far from the samples for kernels with unbounded support. The struct field
+            of the estimates is then named `log_density`.
+        weights (IntoExprColumn | None): Optional column of lists of non-negative
+            numeric weights, one per sample. Bandwidth rules use the effective sample
+            size.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): Either a rule of thumb
+            ("silverman" or "scott"), a plug-in selector ("sj" or "isj"), a
+            cross-validation selector ("likelihood_cv" or "least_squares_cv") or a
+            fixed positive bandwidth (a timedelta for temporal columns).
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors. Defaults to a range around
+            Silverman's bandwidth.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        method (Method): "exact" sums the contributions of all samples at every
+            evaluation point. "fft" bins the samples onto a regular grid of at least
+            4096 points and at least 32 points per bandwidth and convolves them with
+            the kernel by FFT, which is much faster for large groups at the cost of a
+            small approximation error. Groups whose range spans more than about 8000
+            bandwidths, e.g. because of outliers, would need a grid of more than 2^18
+            points and are evaluated exactly instead. "tree" sorts the
+            samples and neglects distant samples as long as the error stays within
+            `atol + rtol * density`, which is exact for zero tolerances and much faster
+            for compact kernels or when evaluating at many points.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled. "drop" ignores null samples,
+            "propagate" returns null if a group contains null samples and "error"
+            raises. Null evaluation points and null rows always yield nulls unless
+            the policy is "error".
+        nan_policy (NanPolicy): How NaN and infinite values are handled. "drop"
+            ignores non-finite samples, "null" returns null if a group contains
+            non-finite samples and "error" raises. Non-finite evaluation points yield
+            null densities unless the policy is "error".
+    """
+    args = [expr, eval_points] if weights is None else [expr, eval_points, weights]
+    return register_plugin_function(
+        args=args,
+        plugin_path=LIB,
+        function_name="kde_dynamic_evals",
+        is_elementwise=True,
+        kwargs={
+            "output": output,
+            **_kde_options(
+                estimate="log_density" if log else "density",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_cdf(
+    expr: IntoExprColumn,
+    *,
+    eval_points: list[TemporalValue | None] | None = None,
+    n_points: int = 200,
+    cut: float = 3.0,
+    grid: GridRange = "local",
+    output: KdeOutput | None = None,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """Cumulative distribution function (CDF) of the KDE, as an aggregation.
+
+    The CDF integrates each kernel analytically, so that `1 - kde_cdf(...)` gives
+    exceedance probabilities without integrating the density numerically. All
+    arguments have the same meaning as in `kde`; with `output="points"` or
+    `output="columns"` the struct field of the estimates is named `cdf`.
+
+    Args:
+        expr (IntoExprColumn): Which numeric column to aggregate into a population.
+        eval_points (list[TemporalValue | None] | None): At which points to evaluate
+            the CDF.
+        n_points (int): The number of points of a generated grid.
+        cut (float): How far a generated grid extends beyond the sample range, in
+            bandwidths.
+        grid (GridRange): The range of a generated grid, see `kde`.
+        output (KdeOutput | None): The output layout, see `kde`.
+        weights (IntoExprColumn | None): Optional non-negative numeric column with
+            the weight of each sample.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): The bandwidth rule or a fixed
+            positive bandwidth, see `kde`.
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        method (Method): "exact", "fft" or "tree", see `kde`. "fft" integrates the
+            binned density, "tree" counts distant samples left of each evaluation
+            point in full.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled, see `kde`.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: The CDF evaluated at the given points, as Float32 for Float32 input
+            and Float64 otherwise.
+    """
+    args = [expr] if weights is None else [expr, weights]
+    return register_plugin_function(
+        args=args,
+        plugin_path=LIB,
+        function_name="kde_agg",
+        is_elementwise=False,
+        returns_scalar=_default_output(eval_points, output) != "points",
+        kwargs={
+            **_eval_options(
+                eval_points=eval_points,
+                n_points=n_points,
+                cut=cut,
+                grid=grid,
+                output=output,
+            ),
+            **_kde_options(
+                estimate="cdf",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_cdf_static_evals(
+    expr: IntoExprColumn,
+    *,
+    eval_points: list[TemporalValue | None] | None = None,
+    n_points: int = 200,
+    cut: float = 3.0,
+    grid: GridRange = "local",
+    output: KdeOutput | None = None,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """
+    Cumulative distribution function (CDF) of the KDE on already aggregated data.
+    Takes a column of lists of floats and evaluates the CDF at the given points. All
+    arguments have the same meaning as in `kde_static_evals`.
+
+    Args:
+        expr (IntoExprColumn): Column of lists of numeric values, e.g.
+            pl.List(pl.Float32).
+        eval_points (list[TemporalValue | None] | None): At which points to evaluate
+            the CDF.
+        n_points (int): The number of points of a generated grid.
+        cut (float): How far a generated grid extends beyond the sample range, in
+            bandwidths.
+        grid (GridRange): The range of a generated grid, see `kde_static_evals`.
+        output (KdeOutput | None): The output layout, see `kde_static_evals`.
+        weights (IntoExprColumn | None): Optional column of lists of non-negative
+            numeric weights, one per sample.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): The bandwidth rule or a fixed
+            positive bandwidth, see `kde`.
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        method (Method): "exact", "fft" or "tree", see `kde_cdf`.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled, see `kde`.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: The CDF evaluated at the given points, or on a generated grid.
+    """
+    args = [expr] if weights is None else [expr, weights]
+    return register_plugin_function(
+        args=args,
+        plugin_path=LIB,
+        function_name="kde_static_evals",
+        is_elementwise=True,
+        kwargs={
+            **_eval_options(
+                eval_points=eval_points,
+                n_points=n_points,
+                cut=cut,
+                grid=grid,
+                output=output,
+            ),
+            **_kde_options(
+                estimate="cdf",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_cdf_dynamic_evals(
+    expr: IntoExprColumn,
+    eval_points: IntoExprColumn,
+    *,
+    output: KdeOutput = "density",
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """
+    Cumulative distribution function (CDF) of the KDE on already aggregated data with
+    evaluation points defined row-wise. All arguments have the same meaning as in
+    `kde_dynamic_evals`.
+
+    Args:
+        expr (IntoExprColumn): Column of lists of numeric values, e.g.
+            pl.List(pl.Float32).
+        eval_points (IntoExprColumn): Column of lists of numeric values, e.g.
+            pl.List(pl.Float32).
+        output (KdeOutput): The output layout, see `kde_dynamic_evals`.
+        weights (IntoExprColumn | None): Optional column of lists of non-negative
+            numeric weights, one per sample.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): The bandwidth rule or a fixed
+            positive bandwidth, see `kde`.
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        method (Method): "exact", "fft" or "tree", see `kde_cdf`.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled, see `kde`.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
     """
+    args = [expr, eval_points] if weights is None else [expr, eval_points, weights]
     return register_plugin_function(
-        args=[expr, eval_points],
+        args=args,
         plugin_path=LIB,
         function_name="kde_dynamic_evals",
         is_elementwise=True,
+        kwargs={
+            "output": output,
+            **_kde_options(
+                estimate="cdf",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_quantile(
+    expr: IntoExprColumn,
+    quantiles: float | Sequence[float],
+    *,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    method: Method = "exact",
+    rtol: float = 0.0,
+    atol: float = 0.0,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """Smoothed quantiles of the KDE, as an aggregation.
+
+    Inverts the CDF of the KDE (see `kde_cdf`) by bisection, which gives smooth
+    quantile estimates even for small groups. The groups, weights and null and NaN
+    handling behave as in `kde`.
+
+    Args:
+        expr (IntoExprColumn): Which numeric column to aggregate into a population.
+        quantiles (float | Sequence[float]): The quantile level, or levels, between 0
+            and 1. The levels 0 and 1 yield the sample range extended by the kernel
+            radius.
+        weights (IntoExprColumn | None): Optional non-negative numeric column with
+            the weight of each sample.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): The bandwidth rule or a fixed
+            positive bandwidth, see `kde`.
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        method (Method): How the CDF is evaluated, see `kde_cdf`.
+        rtol (float): Relative tolerance of the "tree" method.
+        atol (float): Absolute tolerance of the "tree" method.
+        null_policy (NullPolicy): How nulls are handled, see `kde`.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: The quantile for a single level, or the quantiles for a sequence of
+            levels, as Float32 for Float32 input and Float64 otherwise. Quantiles of
+            temporal columns keep the temporal type. Empty groups yield nulls.
+    """
+    scalar = isinstance(quantiles, (int, float))
+    args = [expr] if weights is None else [expr, weights]
+    return register_plugin_function(
+        args=args,
+        plugin_path=LIB,
+        function_name="kde_quantile_agg",
+        is_elementwise=False,
+        returns_scalar=scalar,
+        kwargs={
+            "quantiles": [float(quantiles)]
+            if scalar
+            else [float(q) for q in quantiles],
+            **_kde_options(
+                estimate="cdf",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method=method,
+                rtol=rtol,
+                atol=atol,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_sample(
+    expr: IntoExprColumn,
+    n: int,
+    *,
+    seed: int | None = None,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    lower: TemporalValue | None = None,
+    upper: TemporalValue | None = None,
+    boundary: BoundaryCorrection = "reflection",
+    period: float | timedelta | None = None,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """Draws synthetic samples from the KDE, as an aggregation.
+
+    Each draw picks a sample point, with probability proportional to its weight, and
+    adds kernel noise scaled by the selected bandwidth (a smoothed bootstrap). The
+    groups, weights and null and NaN handling behave as in `kde`. On a bounded or
+    periodic support, the draws follow the corrected density: reflection folds them
+    back into the support, renormalization draws the noise from the kernel truncated
+    to the support, and a period wraps them around.
+
+    Args:
+        expr (IntoExprColumn): Which numeric column to aggregate into a population.
+        n (int): The number of draws per group.
+        seed (int | None): Seed of the random number generator. The same seed and
+            data reproduce the same draws, while different groups draw independently.
+            Without a seed, the draws are seeded from the system clock.
+        weights (IntoExprColumn | None): Optional non-negative numeric column with
+            the weight of each sample.
+        kernel (Kernel): The kernel function, e.g. "gaussian" or "epanechnikov".
+        bandwidth (BandwidthRule | float | timedelta): The bandwidth rule or a fixed
+            positive bandwidth, see `kde`.
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors.
+        lower (TemporalValue | None): Lower bound of the support, see `kde`.
+        upper (TemporalValue | None): Upper bound of the support, see `kde`.
+        boundary (BoundaryCorrection): How the density is corrected near the bounds,
+            see `kde`.
+        period (float | timedelta | None): The period of a periodic support, see `kde`.
+        null_policy (NullPolicy): How nulls are handled, see `kde`.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: A list of `n` draws per group, as Float32 for Float32 input and
+            Float64 otherwise. Draws from temporal columns keep the temporal type.
+            Empty groups yield nulls. A group with a single sample point, which has
+            no bandwidth, repeats that point.
+    """
+    args = [expr] if weights is None else [expr, weights]
+    return register_plugin_function(
+        args=args,
+        plugin_path=LIB,
+        function_name="kde_sample_agg",
+        is_elementwise=False,
+        kwargs={
+            "n": n,
+            "seed": seed,
+            **_kde_options(
+                estimate="density",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=lower,
+                upper=upper,
+                boundary=boundary,
+                period=period,
+                method="exact",
+                rtol=0.0,
+                atol=0.0,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_modes(
+    expr: IntoExprColumn,
+    *,
+    min_prominence: float = 0.0,
+    weights: IntoExprColumn | None = None,
+    kernel: Kernel = "gaussian",
+    bandwidth: BandwidthRule | float | timedelta = "silverman",
+    bw_adjust: float = 1.0,
+    cv_bounds: tuple[float, float] | tuple[timedelta, timedelta] | None = None,
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """Local maxima (modes) of the KDE, as an aggregation.
+
+    The modes are found by mean shift, a gradient ascent on the estimator started
+    from the sample points, so their locations are not limited by the resolution of
+    a grid. The groups, weights and null and NaN handling behave as in `kde`.
+
+    Args:
+        expr (IntoExprColumn): Which numeric column to aggregate into a population.
+        min_prominence (float): Minimum prominence of a mode, as a fraction of the
+            height of the highest mode. The prominence is the height of a mode above
+            the highest valley separating it from a higher mode, or above zero for
+            the highest mode. The default of 0 keeps all local maxima.
+        weights (IntoExprColumn | None): Optional non-negative numeric column with
+            the weight of each sample.
+        kernel (Kernel): The kernel function. The "uniform" and "triangular" kernels
+            have no usable gradient and are not supported.
+        bandwidth (BandwidthRule | float | timedelta): The bandwidth rule or a fixed
+            positive bandwidth, see `kde`.
+        bw_adjust (float): Multiplier applied to the selected bandwidth.
+        cv_bounds (tuple[float, float] | tuple[timedelta, timedelta] | None): Bandwidth
+            search range of the cross-validation selectors.
+        null_policy (NullPolicy): How nulls are handled, see `kde`.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: A list per group of structs with the fields "x", "density" and
+            "prominence", one per mode in increasing order of "x". The location
+            keeps the type of temporal columns, and the density and prominence are
+            Float32 for Float32 input and Float64 otherwise. Empty groups have no
+            modes.
+    """
+    args = [expr] if weights is None else [expr, weights]
+    return register_plugin_function(
+        args=args,
+        plugin_path=LIB,
+        function_name="kde_modes_agg",
+        is_elementwise=False,
+        kwargs={
+            "min_prominence": float(min_prominence),
+            **_kde_options(
+                estimate="density",
+                kernel=kernel,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                cv_bounds=cv_bounds,
+                lower=None,
+                upper=None,
+                boundary="reflection",
+                period=None,
+                method="exact",
+                rtol=0.0,
+                atol=0.0,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde2d(
+    x: IntoExprColumn,
+    y: IntoExprColumn,
+    *,
+    grid_x: list[TemporalValue],
+    grid_y: list[TemporalValue],
+    bandwidth: MultivariateBandwidthRule | float = "scott",
+    bw_adjust: float = 1.0,
+    bandwidth_matrix: BandwidthMatrix = "full",
+    output: GridOutput = "list",
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """Bivariate Kernel Density Estimation (KDE) aggregation with a Gaussian kernel.
+
+    The density is evaluated on the grid spanned by `grid_x` and `grid_y`, i.e. at every
+    point `(grid_x[i], grid_y[j])`.
+
+    Args:
+        x (IntoExprColumn): Which numeric column holds the first coordinate.
+        y (IntoExprColumn): Which numeric column holds the second coordinate.
+        grid_x (list[TemporalValue]): The grid points along the first axis.
+        grid_y (list[TemporalValue]): The grid points along the second axis.
+        bandwidth (MultivariateBandwidthRule | float): Either a rule ("scott" or
+            "silverman") that scales the sample covariance, or a fixed positive
+            bandwidth that is used for both axes.
+        bw_adjust (float): Multiplier applied to the bandwidth.
+        bandwidth_matrix (BandwidthMatrix): "full" uses the full sample covariance,
+            capturing the correlation of x and y, "diagonal" only the variances, i.e. a
+            product kernel, and "scaled_identity" the average variance for both axes.
+        output (GridOutput): "list" returns the densities flattened row-major, so that
+            the density at `(grid_x[i], grid_y[j])` is at `i * len(grid_y) + j`. "array"
+            returns a single nested `Array` of shape `(len(grid_x), len(grid_y))`.
+        null_policy (NullPolicy): How nulls are handled, see `kde`. A sample point is
+            null if either coordinate is null.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: The KDE evaluated on the grid, as Float32 if both columns are Float32
+            and Float64 otherwise.
+    """
+    return register_plugin_function(
+        args=[x, y],
+        plugin_path=LIB,
+        function_name="kde2d_agg",
+        is_elementwise=False,
+        returns_scalar=output == "array",
+        kwargs={
+            "grid_x": [_to_float(v) for v in grid_x],
+            "grid_y": [_to_float(v) for v in grid_y],
+            "temporal": {
+                "grid_x": _is_temporal("grid_x", grid_x),
+                "grid_y": _is_temporal("grid_y", grid_y),
+            },
+            "output": output,
+            **_multivariate_options(
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                bandwidth_matrix=bandwidth_matrix,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
+    )
+
+
+def kde_nd(
+    expr: IntoExprColumn,
+    *,
+    eval_points: Sequence[MultivariatePoint] | pl.Series,
+    bandwidth: MultivariateBandwidthRule | float = "scott",
+    bw_adjust: float = 1.0,
+    bandwidth_matrix: BandwidthMatrix = "full",
+    null_policy: NullPolicy = "drop",
+    nan_policy: NanPolicy = "drop",
+) -> pl.Expr:
+    """Multivariate Kernel Density Estimation (KDE) aggregation with a Gaussian kernel.
+
+    Args:
+        expr (IntoExprColumn): Which column to aggregate into a population. Each sample
+            is either a row of a `pl.Array` of numeric values or a `pl.Struct` of
+            numeric fields.
+        eval_points (Sequence[MultivariatePoint] | pl.Series): At which points to
+            evaluate the KDE, each given by its coordinates in order or, for struct
+            columns, as a mapping from field names to coordinates. A `pl.Series` of
+            `pl.Array` or `pl.Struct` values is accepted as well. The points are fixed
+            when the expression is built, so they cannot be an expression or column.
+        bandwidth (MultivariateBandwidthRule | float): Either a rule ("scott" or
+            "silverman") that scales the sample covariance, or a fixed positive
+            bandwidth that is used for all dimensions.
+        bw_adjust (float): Multiplier applied to the bandwidth.
+        bandwidth_matrix (BandwidthMatrix): "full" uses the full sample covariance,
+            "diagonal" only the variances, i.e. a product kernel, and
+            "scaled_identity" the average variance for all dimensions.
+        null_policy (NullPolicy): How nulls are handled, see `kde`. A sample point is
+            null if the row or any of its coordinates is null.
+        nan_policy (NanPolicy): How NaN and infinite values are handled, see `kde`.
+
+    Returns:
+        pl.Expr: The KDE evaluated at the given points, as Float32 if all coordinates
+            are Float32 and Float64 otherwise.
+    """
+    if isinstance(eval_points, pl.Series):
+        eval_points = eval_points.to_list()
+    return register_plugin_function(
+        args=[expr],
+        plugin_path=LIB,
+        function_name="kde_nd_agg",
+        is_elementwise=False,
+        returns_scalar=False,
+        kwargs={
+            "eval_points": [
+                {k: _to_float(v) for k, v in p.items()}
+                if isinstance(p, Mapping)
+                else [_to_float(v) for v in p]
+                for p in eval_points
+            ],
+            "temporal": _temporal_dimensions(eval_points),
+            **_multivariate_options(
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+                bandwidth_matrix=bandwidth_matrix,
+                null_policy=null_policy,
+                nan_policy=nan_policy,
+            ),
+        },
     )
 
 
-__all__ = ["__version__", "kde", "kde_static_evals", "kde_dynamic_evals"]
+__all__ = [
+    "__version__",
+    "kde",
+    "kde_static_evals",
+    "kde_dynamic_evals",
+    "kde_cdf",
+    "kde_cdf_static_evals",
+    "kde_cdf_dynamic_evals",
+    "kde_quantile",
+    "kde_sample",
+    "kde_modes",
+    "kde2d",
+    "kde_nd",
+]
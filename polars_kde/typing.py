@@ -1,7 +1,10 @@
-from typing import TYPE_CHECKING, Union
+from typing import TYPE_CHECKING, Literal, Union
 
 if TYPE_CHECKING:
     import sys
+    from collections.abc import Mapping, Sequence
+    from datetime import date, datetime, time, timedelta
+
     import polars as pl
 
     if sys.version_info >= (3, 10):
@@ -12,3 +15,46 @@ if TYPE_CHECKING:
 
     IntoExprColumn: TypeAlias = Union[pl.Expr, str, pl.Series]
     PolarsDataType: TypeAlias = Union[DataType, DataTypeClass]
+    TemporalValue: TypeAlias = Union[float, date, datetime, time, timedelta]
+    MultivariatePoint: TypeAlias = Union[
+        Sequence[TemporalValue], Mapping[str, TemporalValue]
+    ]
+    Kernel: TypeAlias = Literal[
+        "gaussian",
+        "normal",
+        "epanechnikov",
+        "triangular",
+        "uniform",
+        "tophat",
+        "biweight",
+        "quartic",
+        "triweight",
+        "cosine",
+        "logistic",
+    ]
+    BandwidthRule: TypeAlias = Literal[
+        "silverman",
+        "scott",
+        "sheather_jones",
+        "sj",
+        "improved_sheather_jones",
+        "isj",
+        "likelihood_cv",
+        "mlcv",
+        "least_squares_cv",
+        "lscv",
+    ]
+    Method: TypeAlias = Literal["exact", "fft", "tree"]
+    BoundaryCorrection: TypeAlias = Literal[
+        "reflection", "reflect", "renormalization", "renormalize"
+    ]
+    Estimate: TypeAlias = Literal["density", "cdf", "log_density"]
+    NullPolicy: TypeAlias = Literal["drop", "propagate", "error"]
+    NanPolicy: TypeAlias = Literal["drop", "null", "error"]
+    MultivariateBandwidthRule: TypeAlias = Literal["scott", "silverman"]
+    BandwidthMatrix: TypeAlias = Literal[
+        "scaled_identity", "identity", "diagonal", "full"
+    ]
+    GridRange: TypeAlias = Literal["local", "global"]
+    GridOutput: TypeAlias = Literal["list", "array"]
+    KdeOutput: TypeAlias = Literal["density", "points", "columns", "array"]
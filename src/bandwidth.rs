@@ -0,0 +1,401 @@
+//! Bandwidth selection rules for the density estimators.
+
+use crate::kernels::{Kernel, SelfConvolution};
+use crate::sample::Sample;
+use serde::Deserialize;
+use std::f64::consts::PI;
+
+/// Number of bins used to approximate the pairwise distances in the Sheather–Jones selector.
+const SJ_BINS: usize = 1000;
+
+/// Number of grid points used by the Improved Sheather–Jones selector.
+const ISJ_GRID_SIZE: usize = 1 << 10;
+
+/// Number of log-spaced candidate bandwidths scanned by the cross-validation selectors.
+const CV_GRID_SIZE: usize = 32;
+
+/// Ratio of the interquartile range to the standard deviation of a normal distribution.
+const NORMAL_IQR: f64 = 1.349;
+
+/// Default cross-validation search range, as multiples of Silverman's bandwidth.
+const CV_DEFAULT_BOUNDS: (f64, f64) = (0.02, 5.0);
+
+/// A rule used to derive the bandwidth from the sample points.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BandwidthRule {
+    Silverman,
+    Scott,
+    #[serde(alias = "sj")]
+    SheatherJones,
+    #[serde(alias = "isj")]
+    ImprovedSheatherJones,
+    #[serde(alias = "mlcv")]
+    LikelihoodCv,
+    #[serde(alias = "lscv")]
+    LeastSquaresCv,
+}
+
+/// The bandwidth of the estimator, either derived from the data by a rule or a fixed positive scalar.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum Bandwidth {
+    Rule(BandwidthRule),
+    Fixed(f64),
+}
+
+impl Bandwidth {
+    /// Returns the bandwidth for the given sample.
+    ///
+    /// Weighted samples use the effective sample size in place of the number of points. The
+    /// cross-validation rules search `cv_bounds`, or a range around Silverman's bandwidth if no
+    /// bounds are given.
+    pub(crate) fn select(
+        &self,
+        sample: &Sample,
+        kernel: Kernel,
+        cv_bounds: Option<(f64, f64)>,
+    ) -> f64 {
+        let cv_bounds = || {
+            cv_bounds.unwrap_or_else(|| {
+                let h = silverman(sample);
+                (CV_DEFAULT_BOUNDS.0 * h, CV_DEFAULT_BOUNDS.1 * h)
+            })
+        };
+        match self {
+            Bandwidth::Rule(BandwidthRule::Silverman) => silverman(sample),
+            Bandwidth::Rule(BandwidthRule::Scott) => scott(sample),
+            Bandwidth::Rule(BandwidthRule::SheatherJones) => sheather_jones(sample),
+            Bandwidth::Rule(BandwidthRule::ImprovedSheatherJones) => {
+                improved_sheather_jones(sample)
+            }
+            Bandwidth::Rule(BandwidthRule::LikelihoodCv) => {
+                likelihood_cv(sample, kernel, cv_bounds())
+            }
+            Bandwidth::Rule(BandwidthRule::LeastSquaresCv) => {
+                least_squares_cv(sample, kernel, cv_bounds())
+            }
+            Bandwidth::Fixed(h) => *h,
+        }
+    }
+}
+
+/// Returns `min(std, IQR / 1.349)`, the robust spread estimate used by the plug-in selectors.
+fn robust_spread(sample: &Sample) -> f64 {
+    let std = sample.std_dev();
+    let iqr = sample.iqr() / NORMAL_IQR;
+    if iqr > 0.0 {
+        std.min(iqr)
+    } else {
+        std
+    }
+}
+
+/// Finds a root of `f` in `[lo, hi]` by bisection, assuming `f(lo)` and `f(hi)` differ in sign.
+fn bisect<F: Fn(f64) -> f64>(f: F, mut lo: f64, mut hi: f64, tol: f64) -> f64 {
+    let mut f_lo = f(lo);
+    while hi - lo > tol {
+        let mid = 0.5 * (lo + hi);
+        let f_mid = f(mid);
+        if f_mid == 0.0 {
+            return mid;
+        }
+        if (f_mid < 0.0) == (f_lo < 0.0) {
+            lo = mid;
+            f_lo = f_mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Minimises `f` on `[lo, hi]` by golden-section search.
+fn golden_section<F: Fn(f64) -> f64>(f: F, mut lo: f64, mut hi: f64, tol: f64) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let mut a = hi - ratio * (hi - lo);
+    let mut b = lo + ratio * (hi - lo);
+    let (mut f_a, mut f_b) = (f(a), f(b));
+    while hi - lo > tol {
+        if f_a < f_b {
+            hi = b;
+            b = a;
+            f_b = f_a;
+            a = hi - ratio * (hi - lo);
+            f_a = f(a);
+        } else {
+            lo = a;
+            a = b;
+            f_a = f_b;
+            b = lo + ratio * (hi - lo);
+            f_b = f(b);
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Minimises `f` over `[lo, hi]` by scanning a logarithmic grid and refining the best candidate
+/// by golden-section search between its neighbours.
+fn minimise_log_scale<F: Fn(f64) -> f64>(f: F, lo: f64, hi: f64) -> f64 {
+    let (log_lo, log_hi) = (lo.ln(), hi.ln());
+    let step = (log_hi - log_lo) / (CV_GRID_SIZE - 1) as f64;
+    let (best, _) = (0..CV_GRID_SIZE)
+        .map(|i| (i, f((log_lo + i as f64 * step).exp())))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+
+    let a = log_lo + best.saturating_sub(1) as f64 * step;
+    let b = log_lo + (best + 1).min(CV_GRID_SIZE - 1) as f64 * step;
+    golden_section(|t| f(t.exp()), a, b, 1e-4).exp()
+}
+
+/// Binned (weighted) counts of the pairwise distances between sample points, as used by the
+/// Sheather–Jones selector.
+struct PairwiseDistances {
+    /// The sum of the squared weights, i.e. the weight of the diagonal pairs `i == j`.
+    diagonal: f64,
+    /// The total weight of all off-diagonal pairs `i != j`.
+    off_diagonal: f64,
+    width: f64,
+    counts: Vec<f64>,
+}
+
+impl PairwiseDistances {
+    /// Bins the sample points onto `SJ_BINS` equally spaced bins and sums the weights of the pairs
+    /// of samples that are `k` bins apart.
+    fn new(sample: &Sample) -> Self {
+        let (min, max) = sample.range();
+        let width = (max - min) * 1.01 / SJ_BINS as f64;
+
+        let mut bins = vec![0.0; SJ_BINS];
+        let mut bins_sq = vec![0.0; SJ_BINS];
+        for (x, w) in sample.iter() {
+            let idx = (((x - min) / width) as usize).min(SJ_BINS - 1);
+            bins[idx] += w;
+            bins_sq[idx] += w * w;
+        }
+
+        let mut counts = vec![0.0; SJ_BINS];
+        counts[0] = bins
+            .iter()
+            .zip(&bins_sq)
+            .map(|(c, c_sq)| (c * c - c_sq) / 2.0)
+            .sum();
+        for (k, count) in counts.iter_mut().enumerate().skip(1) {
+            *count = bins.iter().zip(&bins[k..]).map(|(a, b)| a * b).sum();
+        }
+
+        let total = sample.total_weight();
+        let diagonal = sample.squared_weight();
+        PairwiseDistances {
+            diagonal,
+            off_diagonal: total * total - diagonal,
+            width,
+            counts,
+        }
+    }
+
+    /// Sums `term(delta)` over all pairs `i < j`, where `delta` is the squared scaled distance.
+    fn sum_pairs<F: Fn(f64) -> f64>(&self, h: f64, term: F) -> f64 {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(k, count)| ((k as f64 * self.width / h).powi(2), count))
+            .take_while(|(delta, _)| *delta < 1000.0)
+            .map(|(delta, count)| term(delta) * count)
+            .sum()
+    }
+
+    /// Estimates the integrated squared fourth derivative of the density with pilot bandwidth `h`.
+    fn phi4(&self, h: f64) -> f64 {
+        let sum = self.sum_pairs(h, |d| (-d / 2.0).exp() * (d * d - 6.0 * d + 3.0));
+        (2.0 * sum + 3.0 * self.diagonal) / (self.off_diagonal * h.powi(5) * (2.0 * PI).sqrt())
+    }
+
+    /// Estimates the integrated squared sixth derivative of the density with pilot bandwidth `h`.
+    fn phi6(&self, h: f64) -> f64 {
+        let sum = self.sum_pairs(h, |d| {
+            (-d / 2.0).exp() * (d * d * d - 15.0 * d * d + 45.0 * d - 15.0)
+        });
+        (2.0 * sum - 15.0 * self.diagonal) / (self.off_diagonal * h.powi(7) * (2.0 * PI).sqrt())
+    }
+}
+
+/// The Sheather–Jones "solve-the-equation" plug-in selector, following R's `bw.SJ`.
+///
+/// Where R stops with an error, i.e. when the sample is too sparse to estimate the density functionals or no
+/// root is found in the expanded search range, Silverman's rule is used instead.
+fn sheather_jones(sample: &Sample) -> f64 {
+    let n = sample.effective_size();
+    let scale = robust_spread(sample);
+    if scale <= 0.0 {
+        return silverman(sample);
+    }
+    let distances = PairwiseDistances::new(sample);
+
+    let a = 1.24 * scale * n.powf(-1.0 / 7.0);
+    let b = 1.23 * scale * n.powf(-1.0 / 9.0);
+    let c1 = 1.0 / (2.0 * PI.sqrt() * n);
+    let td = -distances.phi6(b);
+    if !td.is_finite() || td <= 0.0 {
+        return silverman(sample);
+    }
+    let alpha2 = 1.357 * (distances.phi4(a) / td).powf(1.0 / 7.0);
+    if !alpha2.is_finite() {
+        return silverman(sample);
+    }
+    let equation = |h: f64| (c1 / distances.phi4(alpha2 * h.powf(5.0 / 7.0))).powf(0.2) - h;
+
+    let h_max = 1.144 * scale * n.powf(-0.2);
+    let (mut lower, mut upper) = (0.1 * h_max, h_max);
+    let mut tries = 1;
+    while equation(lower) * equation(upper) > 0.0 {
+        if tries > 99 {
+            return silverman(sample);
+        }
+        if tries % 2 == 1 {
+            upper *= 1.2;
+        } else {
+            lower /= 1.2;
+        }
+        tries += 1;
+    }
+    bisect(equation, lower, upper, 1e-6 * h_max)
+}
+
+/// The Improved Sheather–Jones selector of Botev, Grotowski and Kroese (2010), which solves the
+/// plug-in fixed point equation on a discrete cosine transform of the binned data.
+///
+/// Like the reference implementation, the sample size is the number of distinct points. Weights only enter the
+/// histogram, as in KDEpy, so that integer weights give the same bandwidth as repeating the points.
+fn improved_sheather_jones(sample: &Sample) -> f64 {
+    let (min, max) = sample.range();
+    if max <= min {
+        return silverman(sample);
+    }
+    let range = 2.0 * (max - min);
+    let lo = min - (max - min) / 2.0;
+
+    let mut unique = sample.points.clone();
+    unique.sort_by(|a, b| a.total_cmp(b));
+    unique.dedup();
+    let n_unique = unique.len() as f64;
+
+    let m = ISJ_GRID_SIZE;
+    let dx = range / (m - 1) as f64;
+    let mut hist = vec![0.0; m];
+    for (x, w) in sample.iter() {
+        let idx = ((x - lo) / dx) as usize;
+        hist[idx.min(m - 1)] += w;
+    }
+    let total: f64 = hist.iter().sum();
+
+    // squared coefficients of the type II discrete cosine transform of the normalised histogram
+    let a2 = (1..m)
+        .map(|k| {
+            let a_k: f64 = hist
+                .iter()
+                .enumerate()
+                .map(|(j, c)| {
+                    c / total * (PI * k as f64 * (2 * j + 1) as f64 / (2 * m) as f64).cos()
+                })
+                .sum();
+            a_k * a_k
+        })
+        .collect::<Vec<_>>();
+    let i_sq = (1..m).map(|k| (k * k) as f64).collect::<Vec<_>>();
+
+    let functional = |s: i32, t: f64| -> f64 {
+        2.0 * PI.powi(2 * s)
+            * i_sq
+                .iter()
+                .zip(&a2)
+                .map(|(i, a)| i.powi(s) * a * (-i * PI * PI * t).exp())
+                .sum::<f64>()
+    };
+    let fixed_point = |t: f64| -> f64 {
+        let mut f = functional(7, t);
+        for s in (2..7).rev() {
+            let k0 = (1..2 * s).step_by(2).product::<i32>() as f64 / (2.0 * PI).sqrt();
+            let c = (1.0 + 0.5f64.powf(s as f64 + 0.5)) / 3.0;
+            let time = (2.0 * c * k0 / n_unique / f).powf(2.0 / (3.0 + 2.0 * s as f64));
+            f = functional(s, time);
+        }
+        t - (2.0 * n_unique * PI.sqrt() * f).powf(-0.4)
+    };
+
+    let n_clamped = n_unique.clamp(50.0, 1050.0);
+    let mut tol = 1e-12 + 0.01 * (n_clamped - 50.0) / 1000.0;
+    while fixed_point(tol) <= 0.0 && tol < 0.1 {
+        tol = (2.0 * tol).min(0.1);
+    }
+    let t_star = if fixed_point(tol) > 0.0 {
+        bisect(fixed_point, 0.0, tol, 1e-14)
+    } else {
+        golden_section(|t| fixed_point(t).abs(), 0.0, 0.1, 1e-14)
+    };
+
+    t_star.sqrt() * range
+}
+
+/// Likelihood cross-validation: maximises the weighted leave-one-out log-likelihood of the sample points.
+fn likelihood_cv(sample: &Sample, kernel: Kernel, (lo, hi): (f64, f64)) -> f64 {
+    let total = sample.total_weight();
+    let negative_log_likelihood = |h: f64| -> f64 {
+        -sample
+            .iter()
+            .enumerate()
+            .map(|(i, (xi, wi))| {
+                let density: f64 = sample
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, (xj, wj))| wj * kernel.pdf((xi - xj) / h))
+                    .sum();
+                wi * (density / ((total - wi) * h)).ln()
+            })
+            .sum::<f64>()
+    };
+    minimise_log_scale(negative_log_likelihood, lo, hi)
+}
+
+/// Least-squares cross-validation: minimises the estimated integrated squared error
+/// `∫f² - 2 Σ (w_i / W) f_{-i}(x_i)`.
+fn least_squares_cv(sample: &Sample, kernel: Kernel, (lo, hi): (f64, f64)) -> f64 {
+    let total = sample.total_weight();
+    let convolution = SelfConvolution::of(kernel);
+    let score = |h: f64| -> f64 {
+        let (mut squared, mut leave_one_out) = (0.0, 0.0);
+        for (i, (xi, wi)) in sample.iter().enumerate() {
+            for (xj, wj) in sample.iter().skip(i + 1) {
+                let u = (xi - xj) / h;
+                squared += wi * wj * convolution.eval(u);
+                leave_one_out +=
+                    wi * wj * kernel.pdf(u) * (1.0 / (total - wi) + 1.0 / (total - wj));
+            }
+        }
+        // pairs are counted once, the diagonal only contributes to the integrated squared density
+        (2.0 * squared + sample.squared_weight() * convolution.eval(0.0)) / (total * total * h)
+            - 2.0 * leave_one_out / (total * h)
+    };
+    minimise_log_scale(score, lo, hi)
+}
+
+/// Silverman's rule of thumb: `0.9 * min(std, IQR / 1.349) * n^(-1/5)`.
+///
+/// Reproduces the rule of the `kernel-density-estimation` crate, including its quartiles, which are order
+/// statistics of rank `round(q (n + 1))`. Falls back to the standard deviation when the IQR is zero.
+fn silverman(sample: &Sample) -> f64 {
+    let std = sample.std_dev();
+    let iqr = (sample.rank_quantile(0.75) - sample.rank_quantile(0.25)) / NORMAL_IQR;
+    let spread = if iqr > 0.0 { std.min(iqr) } else { std };
+    0.9 * spread * sample.effective_size().powf(-0.2)
+}
+
+/// Scott's rule of thumb: `std * n^(-1/5)`.
+///
+/// Follows `scipy.stats.gaussian_kde` and `seaborn.kdeplot`, which scale the (weighted) standard deviation by
+/// the effective sample size.
+fn scott(sample: &Sample) -> f64 {
+    sample.std_dev() * sample.effective_size().powf(-0.2)
+}
@@ -0,0 +1,191 @@
+//! Binned kernel density estimation.
+//!
+//! The sample is linearly binned onto a regular grid and convolved with the kernel by FFT, so that
+//! the cost grows with the number of samples plus `M log M` for a grid of `M` points, rather than
+//! with the product of the number of samples and evaluation points. The densities on the grid, or
+//! their cumulative integral, are linearly interpolated back to the evaluation points.
+
+use crate::kernels::Kernel;
+use crate::sample::Sample;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// Smallest number of grid points the sample is binned onto.
+const FFT_MIN_GRID_SIZE: usize = 1 << 12;
+
+/// Largest number of grid points the sample is binned onto. Samples whose range spans too many bandwidths for
+/// this grid, e.g. because of outliers, are not binned.
+const FFT_MAX_GRID_SIZE: usize = 1 << 18;
+
+/// Smallest number of grid steps per bandwidth, which bounds the error of the binning and the interpolation
+/// to about `2.5e-4` of the largest density.
+const FFT_STEPS_PER_BANDWIDTH: f64 = 32.0;
+
+/// The KDE of a binned sample, evaluated on the regular grid `lo + i * delta`.
+struct BinnedDensity {
+    lo: f64,
+    delta: f64,
+    densities: Vec<f64>,
+}
+
+impl BinnedDensity {
+    /// Bins the sample onto a grid spanning the sample range extended by the kernel radius on both
+    /// sides, so that the density outside of the grid is negligible (or exactly zero for compact
+    /// kernels), and convolves it with the kernel.
+    ///
+    /// The grid has at least `FFT_STEPS_PER_BANDWIDTH` steps per bandwidth. Returns `None` if that
+    /// takes more than `FFT_MAX_GRID_SIZE` points.
+    fn new(sample: &Sample, kernel: Kernel, bandwidth: f64) -> Option<Self> {
+        let (min, max) = sample.range();
+        let lo = min - kernel.radius() * bandwidth;
+        let hi = max + kernel.radius() * bandwidth;
+        let steps = ((hi - lo) / bandwidth * FFT_STEPS_PER_BANDWIDTH).ceil();
+        if steps.is_nan() || steps >= FFT_MAX_GRID_SIZE as f64 {
+            return None;
+        }
+        let m = (steps as usize + 1).max(FFT_MIN_GRID_SIZE);
+        let delta = (hi - lo) / (m - 1) as f64;
+
+        // linear binning: each sample point is split between its two neighbouring grid points
+        let mut counts = vec![0.0; m];
+        for (x, w) in sample.iter() {
+            let pos = (x - lo) / delta;
+            let idx = (pos as usize).min(m - 2);
+            let frac = pos - idx as f64;
+            counts[idx] += w * (1.0 - frac);
+            counts[idx + 1] += w * frac;
+        }
+
+        let lags = ((kernel.radius() * bandwidth / delta).ceil() as usize).min(m - 1);
+        let norm = 1.0 / (sample.total_weight() * bandwidth);
+        let densities = convolve(&counts, kernel, bandwidth / delta, lags)
+            .into_iter()
+            // the FFT round-off may leave tiny negative values far from the sample
+            .map(|density| (density * norm).max(0.0))
+            .collect();
+
+        Some(BinnedDensity {
+            lo,
+            delta,
+            densities,
+        })
+    }
+
+    /// Linearly interpolates `values` on the grid at `x`, returning `below` or `above` outside of it.
+    fn interpolate(&self, values: &[f64], x: f64, below: f64, above: f64) -> f64 {
+        let m = values.len();
+        let pos = (x - self.lo) / self.delta;
+        if pos < 0.0 {
+            return below;
+        }
+        if pos > (m - 1) as f64 {
+            return above;
+        }
+        let idx = (pos as usize).min(m - 2);
+        let frac = pos - idx as f64;
+        values[idx] * (1.0 - frac) + values[idx + 1] * frac
+    }
+}
+
+/// Computes the KDE of a (weighted) sample at the evaluation points by binning the sample onto a grid.
+///
+/// Returns `None` if the sample range spans too many bandwidths for the grid, see `BinnedDensity::new`.
+pub(crate) fn binned_kde(
+    sample: &Sample,
+    eval_points: &[f64],
+    kernel: Kernel,
+    bandwidth: f64,
+) -> Option<Vec<f64>> {
+    let binned = BinnedDensity::new(sample, kernel, bandwidth)?;
+    Some(
+        eval_points
+            .iter()
+            .map(|&x| binned.interpolate(&binned.densities, x, 0.0, 0.0))
+            .collect(),
+    )
+}
+
+/// The CDF of the KDE of a binned sample, integrated once on the grid so that it can be evaluated
+/// repeatedly, e.g. when inverting it.
+pub(crate) struct BinnedCdf {
+    binned: BinnedDensity,
+    cdf: Vec<f64>,
+}
+
+impl BinnedCdf {
+    /// Integrates the binned density with the trapezoidal rule.
+    ///
+    /// The integral is normalised by its value at the end of the grid, so that the CDF reaches one.
+    /// Returns `None` if the sample range spans too many bandwidths for the grid, like `binned_kde`.
+    pub(crate) fn new(sample: &Sample, kernel: Kernel, bandwidth: f64) -> Option<Self> {
+        let binned = BinnedDensity::new(sample, kernel, bandwidth)?;
+        let mut cdf = Vec::with_capacity(binned.densities.len());
+        cdf.push(0.0);
+        for pair in binned.densities.windows(2) {
+            cdf.push(cdf[cdf.len() - 1] + 0.5 * (pair[0] + pair[1]) * binned.delta);
+        }
+        let total = cdf[cdf.len() - 1];
+        cdf.iter_mut().for_each(|c| *c /= total);
+        Some(BinnedCdf { binned, cdf })
+    }
+
+    /// Interpolates the CDF at the evaluation points.
+    pub(crate) fn evaluate(&self, eval_points: &[f64]) -> Vec<f64> {
+        eval_points
+            .iter()
+            .map(|&x| self.binned.interpolate(&self.cdf, x, 0.0, 1.0))
+            .collect()
+    }
+}
+
+/// Computes the CDF of the KDE of a (weighted) sample at the evaluation points, see `BinnedCdf`.
+pub(crate) fn binned_cdf(
+    sample: &Sample,
+    eval_points: &[f64],
+    kernel: Kernel,
+    bandwidth: f64,
+) -> Option<Vec<f64>> {
+    Some(BinnedCdf::new(sample, kernel, bandwidth)?.evaluate(eval_points))
+}
+
+/// Convolves the binned counts with the kernel evaluated at the lags `-lags..=lags` (in grid steps)
+/// by FFT.
+///
+/// # Arguments
+///
+/// * `counts` - The binned sample weights.
+/// * `kernel` - The kernel function.
+/// * `scale` - The bandwidth in grid steps.
+/// * `lags` - The largest lag at which the kernel is non-negligible.
+fn convolve(counts: &[f64], kernel: Kernel, scale: f64, lags: usize) -> Vec<f64> {
+    // zero padding to at least `m + lags` points prevents the circular convolution from wrapping around
+    let size = (counts.len() + 2 * lags).next_power_of_two();
+
+    let mut signal = vec![Complex::new(0.0, 0.0); size];
+    for (s, &c) in signal.iter_mut().zip(counts) {
+        s.re = c;
+    }
+    let mut filter = vec![Complex::new(0.0, 0.0); size];
+    for k in 0..=lags {
+        let value = kernel.pdf(k as f64 / scale);
+        filter[k].re = value;
+        if k > 0 {
+            filter[size - k].re = value;
+        }
+    }
+
+    let mut planner = FftPlanner::new();
+    let forward = planner.plan_fft_forward(size);
+    forward.process(&mut signal);
+    forward.process(&mut filter);
+    for (s, f) in signal.iter_mut().zip(&filter) {
+        *s *= f;
+    }
+    planner.plan_fft_inverse(size).process(&mut signal);
+
+    signal
+        .iter()
+        .take(counts.len())
+        .map(|c| c.re / size as f64)
+        .collect()
+}
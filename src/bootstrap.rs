@@ -0,0 +1,140 @@
+//! Smooth bootstrap: drawing new values from a KDE.
+//!
+//! Each draw picks a sample point with probability proportional to its weight and adds kernel noise
+//! scaled by the bandwidth. The random numbers come from SplitMix64 rather than an external crate,
+//! so that a seed reproduces the same draws across versions.
+//!
+//! On a bounded or periodic support, the draws follow the corrected estimate:
+//!
+//! - Reflection folds the draws back into the support at its bounds.
+//! - Renormalisation picks each sample point with its renormalised weight and draws the noise from
+//!   the kernel truncated to the support.
+//! - A periodic support wraps the draws around the period.
+
+use crate::boundary::{correct_boundary, BoundaryCorrection};
+use crate::kernels::Kernel;
+use crate::sample::Sample;
+
+/// The support that the draws are confined to.
+pub(crate) enum Support {
+    /// The whole real line.
+    Unbounded,
+    /// The interval `[lower, upper]`, one end of which may be infinite, with its boundary correction.
+    Bounded(f64, f64, BoundaryCorrection),
+    /// The interval `[0, period)`, around which the draws wrap.
+    Periodic(f64),
+}
+
+/// The SplitMix64 pseudo-random number generator.
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    /// Returns the next 64 random bits.
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniform random number in the open interval `(0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 0.5) / (1u64 << 53) as f64
+    }
+}
+
+/// Derives the seed of a group from the given seed and the group's sample, so that groups draw
+/// independent noise while the same seed and data reproduce the same draws.
+fn group_seed(seed: u64, sample: &Sample) -> u64 {
+    sample.iter().fold(seed, |state, (x, w)| {
+        SplitMix64::new(state ^ x.to_bits() ^ w.to_bits().rotate_left(32)).next_u64()
+    })
+}
+
+/// Draws `n` values from the KDE of a (weighted) sample, which must have at least one point.
+///
+/// Without a bandwidth, i.e. for a single sample point, the draws are that point itself.
+pub(crate) fn draw(
+    sample: &Sample,
+    n: usize,
+    kernel: Kernel,
+    bandwidth: Option<f64>,
+    support: Support,
+    seed: u64,
+) -> Vec<f64> {
+    let mut rng = SplitMix64::new(group_seed(seed, sample));
+    let renormalized = match (&support, bandwidth) {
+        (Support::Bounded(lower, upper, BoundaryCorrection::Renormalization), Some(bandwidth)) => {
+            Some(correct_boundary(
+                sample,
+                kernel,
+                bandwidth,
+                *lower,
+                *upper,
+                BoundaryCorrection::Renormalization,
+            ))
+        }
+        _ => None,
+    };
+    let picked = renormalized.as_ref().unwrap_or(sample);
+    let mut cumulative = Vec::with_capacity(picked.len());
+    let mut total = 0.0;
+    for (_, w) in picked.iter() {
+        total += w;
+        cumulative.push(total);
+    }
+
+    (0..n)
+        .map(|_| {
+            let target = rng.next_f64() * total;
+            let idx = cumulative
+                .partition_point(|&c| c <= target)
+                .min(picked.len() - 1);
+            let point = picked.points[idx];
+            let bandwidth = match bandwidth {
+                Some(bandwidth) => bandwidth,
+                None => return point,
+            };
+            match support {
+                Support::Unbounded => point + kernel.quantile(rng.next_f64()) * bandwidth,
+                Support::Periodic(period) => {
+                    (point + kernel.quantile(rng.next_f64()) * bandwidth).rem_euclid(period)
+                }
+                Support::Bounded(lower, upper, BoundaryCorrection::Reflection) => fold(
+                    point + kernel.quantile(rng.next_f64()) * bandwidth,
+                    lower,
+                    upper,
+                ),
+                Support::Bounded(lower, upper, BoundaryCorrection::Renormalization) => {
+                    // inverse transform sampling of the kernel truncated to the support
+                    let below = kernel.cdf((lower - point) / bandwidth);
+                    let above = kernel.cdf((upper - point) / bandwidth);
+                    let p = below + rng.next_f64() * (above - below);
+                    (point + kernel.quantile(p) * bandwidth).clamp(lower, upper)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Folds `x` into `[lower, upper]` by reflecting it at the finite bounds, repeatedly if both are finite.
+fn fold(x: f64, lower: f64, upper: f64) -> f64 {
+    if lower.is_finite() && upper.is_finite() {
+        let width = upper - lower;
+        let offset = (x - lower).rem_euclid(2.0 * width);
+        lower + offset.min(2.0 * width - offset)
+    } else if lower.is_finite() {
+        x.max(2.0 * lower - x)
+    } else if upper.is_finite() {
+        x.min(2.0 * upper - x)
+    } else {
+        x
+    }
+}
@@ -0,0 +1,103 @@
+//! Boundary correction for samples with a bounded support.
+//!
+//! Near a bound, the kernels of the plain KDE leak mass outside of the support. Both corrections
+//! rewrite the sample, so that any method can evaluate the corrected estimate, which is then
+//! rescaled by the total weight of the rewritten sample and truncated to the support:
+//!
+//! - Reflection mirrors each sample point at the bounds, adding the leaked mass back inside. With
+//!   two bounds, the images of repeated reflections are included as long as they are within the
+//!   kernel radius of the support, but at most `MAX_REFLECTION_WIDTHS` support widths away. If that
+//!   cuts off kernel mass, the weights of the remaining images are scaled up to make up for it.
+//! - Renormalisation divides the weight of each sample point by the mass of its kernel within the
+//!   support, so that every kernel integrates to its weight on the support.
+
+use crate::kernels::Kernel;
+use crate::sample::Sample;
+use serde::Deserialize;
+
+/// Largest distance, in support widths, of the images of repeated reflections from the support, which caps the
+/// number of images per sample point when the support is narrow compared to the bandwidth.
+const MAX_REFLECTION_WIDTHS: f64 = 32.0;
+
+/// How the estimate is corrected near the bounds of the support.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BoundaryCorrection {
+    #[serde(alias = "reflect")]
+    Reflection,
+    #[serde(alias = "renormalize")]
+    Renormalization,
+}
+
+/// Returns the images of the sample point `x` under the reflections at the finite bounds, i.e. `x`
+/// itself and those images within `reach` of the support.
+fn reflections(x: f64, lower: f64, upper: f64, reach: f64) -> Vec<f64> {
+    if lower.is_finite() && upper.is_finite() {
+        // the reflections at both bounds generate the translations by twice the width of the support
+        let period = 2.0 * (upper - lower);
+        let mut images = Vec::new();
+        for base in [x, 2.0 * lower - x] {
+            let first = ((lower - reach - base) / period).ceil() as i64;
+            let last = ((upper + reach - base) / period).floor() as i64;
+            images.extend((first..=last).map(|k| base + k as f64 * period));
+        }
+        images
+    } else {
+        let mirrors = [2.0 * lower - x, 2.0 * upper - x];
+        std::iter::once(x)
+            .chain(mirrors.into_iter().filter(|m| m.is_finite()))
+            .filter(|&m| m >= lower - reach && m <= upper + reach)
+            .collect()
+    }
+}
+
+/// Rewrites a (weighted) sample within the support `[lower, upper]`, one of which may be infinite, according to
+/// the boundary correction. The KDE of the rewritten sample, scaled by the ratio of the total weights, integrates
+/// to one on the support.
+pub(crate) fn correct_boundary(
+    sample: &Sample,
+    kernel: Kernel,
+    bandwidth: f64,
+    lower: f64,
+    upper: f64,
+    correction: BoundaryCorrection,
+) -> Sample {
+    match correction {
+        BoundaryCorrection::Reflection => {
+            let reach = kernel.radius() * bandwidth;
+            let capped = reach.min(MAX_REFLECTION_WIDTHS * (upper - lower));
+            let (points, weights) = sample
+                .iter()
+                .flat_map(|(x, w)| {
+                    let images = reflections(x, lower, upper, capped);
+                    let w = if capped < reach {
+                        // the images cut off by the cap would add the missing mass back inside
+                        let mass: f64 = images
+                            .iter()
+                            .map(|m| {
+                                kernel.cdf((upper - m) / bandwidth)
+                                    - kernel.cdf((lower - m) / bandwidth)
+                            })
+                            .sum();
+                        w / mass
+                    } else {
+                        w
+                    };
+                    images.into_iter().map(move |image| (image, w))
+                })
+                .unzip();
+            Sample::new(points, weights)
+        }
+        BoundaryCorrection::Renormalization => {
+            let weights = sample
+                .iter()
+                .map(|(x, w)| {
+                    let mass =
+                        kernel.cdf((upper - x) / bandwidth) - kernel.cdf((lower - x) / bandwidth);
+                    w / mass
+                })
+                .collect();
+            Sample::new(sample.points.clone(), weights)
+        }
+    }
+}
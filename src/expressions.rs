@@ -2,79 +2,1970 @@
 ///
 /// # Functions
 ///
-/// - `same_output_type`: A helper function that returns the same output type as the input fields.
+/// - `kde_agg_output_type`, `kde_static_output_type`, `kde_dynamic_output_type`, `value_output_type` (shared by `kde_quantile_agg` and `kde_sample_agg`), `kde_modes_output_type`, `kde2d_output_type` and `kde_nd_output_type`: Helper functions that return the output field of each KDE function, in the requested output layout.
 /// - `kde_dynamic_evals`: Applies KDE to a series of sample points and evaluation points, returning the resulting density estimates as a series.
 /// - `kde_static_evals`: Applies KDE to a series of sample points with evaluation points provided via keyword arguments, returning the resulting density estimates as a series.
 /// - `kde_agg`: Aggregates KDE results for a series of sample points with evaluation points provided via keyword arguments, returning the resulting density estimates as a series.
+/// - `kde_quantile_agg`: Aggregates the quantiles of the KDE of a series of sample points, with quantile levels provided via keyword arguments.
+/// - `kde_sample_agg`: Draws synthetic samples from the KDE of a series of sample points, with the number of draws and the seed provided via keyword arguments.
+/// - `kde_modes_agg`: Aggregates the local maxima of the KDE of a series of sample points, found by mean shift and filtered by their prominence.
+/// - `kde2d_agg`: Aggregates a bivariate KDE of two series of sample points, evaluated on a grid provided via keyword arguments.
+/// - `kde_nd_agg`: Aggregates a multivariate KDE of a series of `Array` or `Struct` sample points, with evaluation points provided via keyword arguments.
 ///
 /// # Structs
 ///
-/// - `KdeKwargs`: A struct for holding keyword arguments for KDE functions, specifically the evaluation points.
+/// - `KdeOptions`: A struct for holding the estimator options shared by all KDE functions, such as the kernel, the bandwidth and the bounds or the period of the support.
+/// - `KdeKwargs`: A struct for holding keyword arguments for KDE functions, specifically the evaluation points and the output layout.
+/// - `DynamicKwargs`: A struct for holding keyword arguments for the KDE with evaluation points given per list row.
+/// - `KdeQuantileKwargs`: A struct for holding keyword arguments for KDE quantiles, specifically the quantile levels.
+/// - `KdeSampleKwargs`: A struct for holding keyword arguments for drawing from the KDE, specifically the number of draws and the seed.
+/// - `KdeModesKwargs`: A struct for holding keyword arguments for mode finding, specifically the minimum prominence.
+/// - `MultivariateOptions`: A struct for holding the options of the multivariate estimator.
+/// - `Kde2dKwargs`: A struct for holding keyword arguments for the bivariate KDE, specifically the grid.
+/// - `KdeNdKwargs`: A struct for holding keyword arguments for the multivariate KDE, specifically the evaluation points.
 ///
 /// # Example
 ///
-/// ```rust
-/// use kernel_density_estimation::prelude::*;
-/// use polars::prelude::*;
-/// use serde::Deserialize;
-///
-/// #[derive(Deserialize)]
-/// struct KdeKwargs {
-///     eval_points: Vec<f32>,
-/// }
-///
-/// fn main() -> PolarsResult<()> {
-///     // Example usage of kde function
-///     let sample_series = Series::new("samples", vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
-///     let kwargs = KdeKwargs { eval_points: vec![1.0, 2.0, 3.0] };
-///     let result = kde(&[sample_series], kwargs)?;
-///     println!("{:?}", result);
-///     Ok(())
-/// }
+/// The functions are called from Python through the wrappers of the `polars_kde` package, which pass their keyword
+/// arguments as the structs above, e.g. `kde_agg` with a `KdeKwargs`:
+///
+/// ```python
+/// import polars as pl
+/// import polars_kde as pkde
+///
+/// df = pl.DataFrame({"id": [0, 0, 0, 1, 1, 1], "a": [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]})
+/// df.group_by("id").agg(kde=pkde.kde(pl.col("a"), eval_points=[1.0, 2.0, 3.0]))
 /// ```
-use kernel_density_estimation::prelude::*;
+use crate::bandwidth::Bandwidth;
+use crate::binned::{binned_cdf, binned_kde, BinnedCdf};
+use crate::bootstrap::{draw, Support};
+use crate::boundary::{correct_boundary, BoundaryCorrection};
+use crate::kernels::{Kernel, QUANTILE_BISECTIONS};
+use crate::modes::find_modes;
+use crate::multivariate::{
+    BandwidthMatrix, MultivariateBandwidth, MultivariateKde, MultivariateSample,
+};
+use crate::periodic::{unwrap_sample, wrap_sample};
+use crate::sample::Sample;
+use crate::tree::{tree_cdf, tree_kde};
 use polars::prelude::*;
 use polars_core::utils::align_chunks_binary;
 use pyo3_polars::derive::polars_expr;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Density, relative to the largest possible density `1 / h`, below which the log-density of the approximate methods
+/// is recomputed exactly, since their absolute errors dominate the logarithm of small densities.
+const LOG_DENSITY_FLOOR: f64 = 1e-10;
+
+/// Number of sample points shown to identify a group in error messages.
+const LOCATION_PREVIEW: usize = 3;
+
+/// Number of nanoseconds in a day, the end of the axis of `Time` values.
+const NS_PER_DAY: f64 = 86_400e9;
+
+/// How null sample points, null evaluation points and null list rows are handled.
+///
+/// - `Drop`: null sample points are ignored, null evaluation points and null list rows yield nulls.
+/// - `Propagate`: a null sample point makes the whole output row null, otherwise like `Drop`.
+/// - `Error`: any null raises an error.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum NullPolicy {
+    Drop,
+    Propagate,
+    Error,
+}
+
+/// How non-finite (NaN and infinite) sample points and evaluation points are handled.
+///
+/// - `Drop`: non-finite sample points are ignored, non-finite evaluation points yield null densities.
+/// - `Null`: a non-finite sample point makes the whole output row null, otherwise like `Drop`.
+/// - `Error`: any non-finite value raises an error.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum NanPolicy {
+    Drop,
+    Null,
+    Error,
+}
+
+/// How the density is evaluated.
+///
+/// - `Exact`: sums the kernel contributions of all sample points at every evaluation point.
+/// - `Fft`: bins the sample onto a regular grid, convolves it with the kernel by FFT and interpolates back to
+///   the evaluation points, which is much faster for large samples and many evaluation points.
+/// - `Tree`: sorts the sample and only sums the contributions of the sample points near each evaluation point,
+///   neglecting distant points within the tolerances `rtol` and `atol`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Method {
+    Exact,
+    Fft,
+    Tree,
+}
+
+/// The function of the estimated distribution that is evaluated.
+///
+/// - `Density`: the probability density function.
+/// - `Cdf`: the cumulative distribution function, i.e. the integral of the density up to each evaluation point.
+/// - `LogDensity`: the natural logarithm of the density, computed with a log-sum-exp over the kernel contributions.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Estimate {
+    Density,
+    Cdf,
+    LogDensity,
+}
+
+impl Estimate {
+    /// Returns the name of the estimated values, which is the name of their field next to the evaluation points.
+    fn name(&self) -> &'static str {
+        match self {
+            Estimate::Density => "density",
+            Estimate::Cdf => "cdf",
+            Estimate::LogDensity => "log_density",
+        }
+    }
+}
+
+/// A struct for holding the estimator options shared by all KDE functions.
+#[derive(Deserialize)]
+struct KdeOptions {
+    estimate: Estimate,
+    kernel: Kernel,
+    bandwidth: Bandwidth,
+    bw_adjust: f64,
+    cv_bounds: Option<(f64, f64)>,
+    method: Method,
+    rtol: f64,
+    atol: f64,
+    null_policy: NullPolicy,
+    nan_policy: NanPolicy,
+    lower: Option<f64>,
+    upper: Option<f64>,
+    boundary: BoundaryCorrection,
+    period: Option<f64>,
+    temporal: HashMap<String, bool>,
+}
+
+impl KdeOptions {
+    /// Returns the support of the sample, which is unbounded on either side without a bound.
+    fn support(&self) -> (f64, f64) {
+        (
+            self.lower.unwrap_or(f64::NEG_INFINITY),
+            self.upper.unwrap_or(f64::INFINITY),
+        )
+    }
+}
+
+/// The range of generated evaluation points.
+///
+/// - `Local`: each group or list row spans its own sample range.
+/// - `Global`: all list rows share one range, spanning the sample ranges of all rows.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum GridRange {
+    Local,
+    Global,
+}
+
+/// A struct for holding the options of generated evaluation points, which are used when no evaluation points
+/// are given.
+#[derive(Deserialize)]
+struct GridOptions {
+    n_points: usize,
+    cut: f64,
+    grid: GridRange,
+}
+
+/// How the densities of a group or list row are returned.
+///
+/// - `Density`: the densities only, in the order of the evaluation points. If the number of evaluation points is
+///   known before evaluation, i.e. given via keyword arguments or generated, they are returned like `Array`.
+/// - `Points`: a list of structs, each holding an evaluation point `x` and its `density`.
+/// - `Columns`: a struct of the list of evaluation points `x` and the list of their `density`.
+/// - `Array`: the densities only, as an `Array` whose width is the number of evaluation points given via keyword
+///   arguments or generated.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum KdeOutput {
+    Density,
+    Points,
+    Columns,
+    Array,
+}
+
+/// A struct for holding keyword arguments for KDE functions, specifically the evaluation points, or the options
+/// to generate them, and the output layout.
+#[derive(Deserialize)]
+struct KdeKwargs {
+    eval_points: Option<Vec<Option<f64>>>,
+    temporal_eval_points: bool,
+    #[serde(flatten)]
+    grid: GridOptions,
+    output: KdeOutput,
+    #[serde(flatten)]
+    options: KdeOptions,
+}
+
+/// A struct for holding keyword arguments for the KDE with evaluation points given per list row, specifically the
+/// output layout.
+#[derive(Deserialize)]
+struct DynamicKwargs {
+    output: KdeOutput,
+    #[serde(flatten)]
+    options: KdeOptions,
+}
+
+/// How a density evaluated on a grid is returned.
+///
+/// - `List`: the densities of all grid points, flattened row-major.
+/// - `Array`: a nested `Array` with one row per point of the first axis.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum GridOutput {
+    List,
+    Array,
+}
+
+/// A struct for holding the options of the multivariate estimator.
+#[derive(Deserialize)]
+struct MultivariateOptions {
+    bandwidth: MultivariateBandwidth,
+    bw_adjust: f64,
+    bandwidth_matrix: BandwidthMatrix,
+    null_policy: NullPolicy,
+    nan_policy: NanPolicy,
+}
+
+/// A struct for holding keyword arguments for the bivariate KDE, specifically the grid and its output layout.
+#[derive(Deserialize)]
+struct Kde2dKwargs {
+    grid_x: Vec<f64>,
+    grid_y: Vec<f64>,
+    temporal: HashMap<String, bool>,
+    output: GridOutput,
+    #[serde(flatten)]
+    options: MultivariateOptions,
+}
+
+/// A struct for holding keyword arguments for KDE quantiles, specifically the quantile levels.
+#[derive(Deserialize)]
+struct KdeQuantileKwargs {
+    quantiles: Vec<f64>,
+    #[serde(flatten)]
+    options: KdeOptions,
+}
+
+/// A struct for holding keyword arguments for drawing from the KDE, specifically the number of draws and the seed.
+///
+/// Without a seed, the draws are seeded from the system clock.
+#[derive(Deserialize)]
+struct KdeSampleKwargs {
+    n: usize,
+    seed: Option<u64>,
+    #[serde(flatten)]
+    options: KdeOptions,
+}
+
+/// A struct for holding keyword arguments for mode finding, specifically the minimum prominence as a fraction of
+/// the height of the highest mode.
+#[derive(Deserialize)]
+struct KdeModesKwargs {
+    min_prominence: f64,
+    #[serde(flatten)]
+    options: KdeOptions,
+}
+
+/// A multivariate evaluation point, given either by its coordinates in order or by the names of the struct fields.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EvalPoint {
+    Coordinates(Vec<f64>),
+    Named(HashMap<String, f64>),
+}
+
+/// A struct for holding keyword arguments for the multivariate KDE, specifically the evaluation points.
+#[derive(Deserialize)]
+struct KdeNdKwargs {
+    eval_points: Vec<EvalPoint>,
+    temporal: HashMap<String, bool>,
+    #[serde(flatten)]
+    options: MultivariateOptions,
+}
+
+/// Validates the estimator options before any computation takes place.
+///
+/// # Arguments
+///
+/// * `options` - The estimator options.
+/// * `dtype` - The data type of the sample points.
+///
+/// # Returns
+///
+/// An error if the fixed bandwidth or the bandwidth multiplier is not a positive number, if the
+/// cross-validation search range is not a valid positive interval, if a tolerance is negative, if the
+/// bounds of the support are not finite or not increasing, if the period is not a positive number or
+/// combined with bounds, or if a value does not match the type of the sample points, see `check_temporal_arg`.
+fn check_options(options: &KdeOptions, dtype: &DataType) -> PolarsResult<()> {
+    for (arg, &temporal) in &options.temporal {
+        check_temporal_arg(arg, temporal, "values", dtype)?;
+    }
+    if let Bandwidth::Fixed(h) = options.bandwidth {
+        polars_ensure!(
+            h > 0.0 && h.is_finite(),
+            ComputeError: "Expected `bandwidth` to be a positive number, got: {}", h
+        );
+    }
+    polars_ensure!(
+        options.bw_adjust > 0.0 && options.bw_adjust.is_finite(),
+        ComputeError: "Expected `bw_adjust` to be a positive number, got: {}", options.bw_adjust
+    );
+    if let Some((lo, hi)) = options.cv_bounds {
+        polars_ensure!(
+            lo > 0.0 && lo < hi && hi.is_finite(),
+            ComputeError: "Expected `cv_bounds` to satisfy 0 < lower < upper, got: ({}, {})", lo, hi
+        );
+    }
+    for (name, tol) in [("rtol", options.rtol), ("atol", options.atol)] {
+        polars_ensure!(
+            tol >= 0.0 && tol.is_finite(),
+            ComputeError: "Expected `{}` to be a non-negative number, got: {}", name, tol
+        );
+    }
+    for (name, bound) in [("lower", options.lower), ("upper", options.upper)] {
+        if let Some(bound) = bound {
+            polars_ensure!(
+                bound.is_finite(),
+                ComputeError: "Expected `{}` to be a finite number, got: {}", name, bound
+            );
+        }
+    }
+    if let (Some(lower), Some(upper)) = (options.lower, options.upper) {
+        polars_ensure!(
+            lower < upper,
+            ComputeError: "Expected `lower` to be less than `upper`, got: {} and {}", lower, upper
+        );
+    }
+    if let Some(period) = options.period {
+        polars_ensure!(
+            period > 0.0 && period.is_finite(),
+            ComputeError: "Expected `period` to be a positive number, got: {}", period
+        );
+        polars_ensure!(
+            options.lower.is_none() && options.upper.is_none(),
+            ComputeError: "A periodic support has no bounds, `period` cannot be combined with `lower` or `upper`"
+        );
+    }
+    Ok(())
+}
+
+/// Validates that the values of a keyword argument, e.g. the evaluation points or a fixed bandwidth, match the
+/// type of the sample points they refer to.
+///
+/// Temporal sample points are measured in nanoseconds, so that their values must be given as temporal values,
+/// which are converted to nanoseconds, rather than as plain numbers of an ambiguous unit. Numeric sample points
+/// conversely require plain numbers.
+///
+/// # Arguments
+///
+/// * `arg` - The name of the keyword argument, used in the error message.
+/// * `temporal` - Whether the values were given as temporal values.
+/// * `name` - The name of the sample points, used in the error message.
+/// * `dtype` - The data type of the sample points.
+///
+/// # Returns
+///
+/// An error if plain numbers are given for temporal sample points, or temporal values for numeric ones.
+fn check_temporal_arg(arg: &str, temporal: bool, name: &str, dtype: &DataType) -> PolarsResult<()> {
+    if dtype.is_temporal() {
+        polars_ensure!(
+            temporal,
+            ComputeError: "Expected `{}` to be given as dates, datetimes, times or timedeltas for `{}` of type {}, got plain numbers", arg, name, dtype
+        );
+    } else {
+        polars_ensure!(
+            !temporal,
+            ComputeError: "Expected `{}` to be given as plain numbers for `{}` of type {}, got temporal values", arg, name, dtype
+        );
+    }
+    Ok(())
+}
+
+/// Validates the quantile levels, which must lie in `[0, 1]`.
+fn check_quantiles(quantiles: &[f64]) -> PolarsResult<()> {
+    for &q in quantiles {
+        polars_ensure!(
+            (0.0..=1.0).contains(&q),
+            ComputeError: "Expected `quantiles` to lie between 0 and 1, got: {}", q
+        );
+    }
+    Ok(())
+}
+
+/// Validates the mode finding options, which require a kernel with a usable gradient for the mean shift.
+fn check_modes(kwargs: &KdeModesKwargs) -> PolarsResult<()> {
+    let kernel = kwargs.options.kernel;
+    polars_ensure!(
+        !matches!(kernel, Kernel::Uniform | Kernel::Triangular),
+        ComputeError: "Expected a kernel with a differentiable density for `kde_modes`, got: {}", format!("{:?}", kernel).to_lowercase()
+    );
+    polars_ensure!(
+        (0.0..=1.0).contains(&kwargs.min_prominence),
+        ComputeError: "Expected `min_prominence` to lie between 0 and 1, got: {}", kwargs.min_prominence
+    );
+    Ok(())
+}
+
+/// Validates the options of generated evaluation points.
+///
+/// # Arguments
+///
+/// * `grid` - The options of the generated evaluation points.
+///
+/// # Returns
+///
+/// An error if there are no points or if the padding is not a non-negative number.
+fn check_grid_options(grid: &GridOptions) -> PolarsResult<()> {
+    polars_ensure!(
+        grid.n_points > 0,
+        ComputeError: "Expected `n_points` to be positive, got: {}", grid.n_points
+    );
+    polars_ensure!(
+        grid.cut >= 0.0 && grid.cut.is_finite(),
+        ComputeError: "Expected `cut` to be a non-negative number, got: {}", grid.cut
+    );
+    Ok(())
+}
+
+/// Validates the multivariate estimator options before any computation takes place.
+///
+/// # Arguments
+///
+/// * `options` - The multivariate estimator options.
+///
+/// # Returns
+///
+/// An error if the fixed bandwidth or the bandwidth multiplier is not a positive number.
+fn check_multivariate_options(options: &MultivariateOptions) -> PolarsResult<()> {
+    if let MultivariateBandwidth::Fixed(h) = options.bandwidth {
+        polars_ensure!(
+            h > 0.0 && h.is_finite(),
+            ComputeError: "Expected `bandwidth` to be a positive number, got: {}", h
+        );
+    }
+    polars_ensure!(
+        options.bw_adjust > 0.0 && options.bw_adjust.is_finite(),
+        ComputeError: "Expected `bw_adjust` to be a positive number, got: {}", options.bw_adjust
+    );
+    Ok(())
+}
+
+/// Validates the points along one axis of an evaluation grid.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument, used in the error message.
+/// * `axis` - The points along the axis.
+///
+/// # Returns
+///
+/// An error if the axis is empty or contains non-finite values.
+fn check_grid_axis(name: &str, axis: &[f64]) -> PolarsResult<()> {
+    polars_ensure!(
+        !axis.is_empty(),
+        ComputeError: "Expected `{}` to contain at least one point", name
+    );
+    if let Some(value) = axis.iter().find(|x| !x.is_finite()) {
+        polars_bail!(
+            ComputeError: "Expected `{}` to contain finite values, got: {}", name, value
+        );
+    }
+    Ok(())
+}
+
+/// A helper function that returns the output field of `kde_agg`, named after the sample points.
+///
+/// The field holds the type of a single row of a group, which becomes a list in an aggregation, unless the
+/// `Density` and `Array` layouts return a single array of the known number of evaluation points per group, and
+/// the `Columns` layout a single struct.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+/// * `kwargs` - A struct containing the evaluation points, or the options to generate them, and the output layout.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn kde_agg_output_type(input_fields: &[Field], kwargs: KdeKwargs) -> PolarsResult<Field> {
+    let field = &input_fields[0];
+    let dtype = kde_dtype(
+        field.name(),
+        field.dtype(),
+        kwargs.output,
+        false,
+        Some(eval_len(&kwargs)),
+        kwargs.eval_points.is_none(),
+        kwargs.options.estimate,
+    )?;
+    Ok(Field::new(field.name().clone(), dtype))
+}
+
+/// A helper function that returns the output field of `kde_static_evals`, named after the lists of sample points.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+/// * `kwargs` - A struct containing the evaluation points, or the options to generate them, and the output layout.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn kde_static_output_type(input_fields: &[Field], kwargs: KdeKwargs) -> PolarsResult<Field> {
+    let field = &input_fields[0];
+    let dtype = kde_dtype(
+        field.name(),
+        list_inner_dtype(field)?,
+        kwargs.output,
+        true,
+        Some(eval_len(&kwargs)),
+        kwargs.eval_points.is_none(),
+        kwargs.options.estimate,
+    )?;
+    Ok(Field::new(field.name().clone(), dtype))
+}
+
+/// A helper function that returns the output field of `kde_dynamic_evals`, named after the lists of sample points.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+/// * `kwargs` - A struct containing the output layout.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn kde_dynamic_output_type(input_fields: &[Field], kwargs: DynamicKwargs) -> PolarsResult<Field> {
+    let field = &input_fields[0];
+    list_float_dtype(input_fields[1].name(), input_fields[1].dtype())?;
+    let dtype = kde_dtype(
+        field.name(),
+        list_inner_dtype(field)?,
+        kwargs.output,
+        true,
+        None,
+        false,
+        kwargs.options.estimate,
+    )?;
+    Ok(Field::new(field.name().clone(), dtype))
+}
+
+/// Returns the number of evaluation points given via keyword arguments, or generated on a grid.
+fn eval_len(kwargs: &KdeKwargs) -> usize {
+    kwargs
+        .eval_points
+        .as_ref()
+        .map_or(kwargs.grid.n_points, |eval_points| eval_points.len())
+}
+
+/// Returns the inner type of a list field, which must be a list of numeric or temporal values.
+fn list_inner_dtype(field: &Field) -> PolarsResult<&DataType> {
+    match field.dtype() {
+        DataType::List(inner) => Ok(inner),
+        dtype => polars_bail!(
+            ComputeError: "Expected `{}` to be a list of numeric or temporal values, got: {}", field.name(), dtype
+        ),
+    }
+}
+
+/// A helper function that returns the output field of `kde_quantile_agg` and `kde_sample_agg`, named after the
+/// sample points.
+///
+/// The quantiles and draws keep the type of temporal values, and have the float type of the densities otherwise.
+/// The field holds the type of a single value, which becomes a list in an aggregation.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn value_output_type(input_fields: &[Field]) -> PolarsResult<Field> {
+    let field = &input_fields[0];
+    let float = float_dtype(field.name(), field.dtype())?;
+    let dtype = if field.dtype().is_temporal() {
+        field.dtype().clone()
+    } else {
+        float
+    };
+    Ok(Field::new(field.name().clone(), dtype))
+}
+
+/// A helper function that returns the output field of `kde_modes_agg`, named after the sample points.
+///
+/// Each mode is a struct of its location `x`, which keeps the type of temporal values, and its `density` and
+/// `prominence`, which have the float type of the densities. The field holds the type of a single mode, which
+/// becomes a list in an aggregation.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn kde_modes_output_type(input_fields: &[Field]) -> PolarsResult<Field> {
+    let field = &input_fields[0];
+    let float = float_dtype(field.name(), field.dtype())?;
+    let x = if field.dtype().is_temporal() {
+        field.dtype().clone()
+    } else {
+        float.clone()
+    };
+    let dtype = DataType::Struct(vec![
+        Field::new("x".into(), x),
+        Field::new("density".into(), float.clone()),
+        Field::new("prominence".into(), float),
+    ]);
+    Ok(Field::new(field.name().clone(), dtype))
+}
+
+/// Returns the type of the KDE of values of type `dtype` in the given output layout.
+///
+/// The densities have the float type of `float_dtype`. The evaluation points `x` keep the type of temporal
+/// values (see `grid_points_dtype` for generated grids), and have the float type of the densities otherwise.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument, used in the error message.
+/// * `dtype` - The data type of the argument.
+/// * `output` - The output layout.
+/// * `list` - Whether each row holds the densities of a whole list row, rather than of a single evaluation point.
+/// * `len` - The number of evaluation points, if it is known before evaluation.
+/// * `generated` - Whether the evaluation points are generated on a grid.
+/// * `estimate` - The estimated function, which names the field of the estimates next to the evaluation points.
+///
+/// # Returns
+///
+/// A result containing the type of the KDE, which is an `Array` for the `Density` layout if the number of
+/// evaluation points is known, or an error if the `Array` layout is requested without knowing it.
+fn kde_dtype(
+    name: &str,
+    dtype: &DataType,
+    output: KdeOutput,
+    list: bool,
+    len: Option<usize>,
+    generated: bool,
+    estimate: Estimate,
+) -> PolarsResult<DataType> {
+    let float = float_dtype(name, dtype)?;
+    let x = if generated && dtype.is_temporal() {
+        grid_points_dtype(dtype)
+    } else if dtype.is_temporal() {
+        dtype.clone()
+    } else {
+        float.clone()
+    };
+    let inner = match (output, len) {
+        (KdeOutput::Density | KdeOutput::Array, Some(len)) => {
+            return Ok(DataType::Array(Box::new(float), len))
+        }
+        (KdeOutput::Density, None) => float,
+        (KdeOutput::Points, _) => DataType::Struct(vec![
+            Field::new("x".into(), x),
+            Field::new(estimate.name().into(), float),
+        ]),
+        (KdeOutput::Columns, _) => {
+            return Ok(DataType::Struct(vec![
+                Field::new("x".into(), DataType::List(Box::new(x))),
+                Field::new(estimate.name().into(), DataType::List(Box::new(float))),
+            ]))
+        }
+        (KdeOutput::Array, None) => polars_bail!(
+            ComputeError: "The `array` output requires evaluation points given via keyword arguments, use `kde_static_evals` instead"
+        ),
+    };
+    Ok(if list {
+        DataType::List(Box::new(inner))
+    } else {
+        inner
+    })
+}
+
+/// Returns the float type of the densities for values of type `dtype`, which must be numeric.
+///
+/// `Float32` values yield `Float32` densities, all other numeric types (floats, signed and unsigned
+/// integers and decimals) are upcast to `Float64`. Temporal values yield `Float64` densities per
+/// nanosecond, see `cast_to_f64`.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument, used in the error message.
+/// * `dtype` - The data type of the argument.
+///
+/// # Returns
+///
+/// A result containing the float type that the densities are returned in.
+fn float_dtype(name: &str, dtype: &DataType) -> PolarsResult<DataType> {
+    match dtype {
+        DataType::Float32 => Ok(DataType::Float32),
+        dt if dt.is_numeric() || dt.is_temporal() => Ok(DataType::Float64),
+        _ => polars_bail!(
+            ComputeError: "Expected `{}` to be numeric or temporal, got: {}", name, dtype
+        ),
+    }
+}
+
+/// Returns the float type of a joint density over several numeric or temporal fields.
+///
+/// The density is returned as `Float32` if all fields are `Float32`, and as `Float64` otherwise.
+///
+/// # Arguments
+///
+/// * `fields` - The fields of the sample coordinates.
+///
+/// # Returns
+///
+/// A result containing the float type that the densities are returned in.
+fn joint_float_dtype(fields: &[Field]) -> PolarsResult<DataType> {
+    let mut dtype = DataType::Float32;
+    for field in fields {
+        if float_dtype(field.name(), field.dtype())? != DataType::Float32 {
+            dtype = DataType::Float64;
+        }
+    }
+    Ok(dtype)
+}
+
+/// Returns the type of a density evaluated on a grid of `nx` by `ny` points.
+///
+/// # Arguments
+///
+/// * `dtype` - The float type of the densities.
+/// * `nx` - The number of points along the first axis.
+/// * `ny` - The number of points along the second axis.
+/// * `output` - The output layout.
+///
+/// # Returns
+///
+/// The float type itself for a flattened list, which becomes a list in an aggregation, or a nested `Array`.
+fn grid_dtype(dtype: DataType, nx: usize, ny: usize, output: GridOutput) -> DataType {
+    match output {
+        GridOutput::List => dtype,
+        GridOutput::Array => DataType::Array(Box::new(DataType::Array(Box::new(dtype), ny)), nx),
+    }
+}
+
+/// A helper function that returns the output field of the bivariate KDE, named after the first input.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+/// * `kwargs` - A struct containing the grid and its output layout.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn kde2d_output_type(input_fields: &[Field], kwargs: Kde2dKwargs) -> PolarsResult<Field> {
+    let dtype = joint_float_dtype(&input_fields[..2])?;
+    Ok(Field::new(
+        input_fields[0].name().clone(),
+        grid_dtype(
+            dtype,
+            kwargs.grid_x.len(),
+            kwargs.grid_y.len(),
+            kwargs.output,
+        ),
+    ))
+}
+
+/// A helper function that returns the output field of `kde_nd_agg`, named after the sample points.
+///
+/// The field holds the float type of the density at a single evaluation point, which becomes a list in an
+/// aggregation.
+///
+/// # Arguments
+///
+/// * `input_fields` - A slice of input fields.
+/// * `kwargs` - A struct containing the evaluation points.
+///
+/// # Returns
+///
+/// A result containing the output field.
+fn kde_nd_output_type(input_fields: &[Field], kwargs: KdeNdKwargs) -> PolarsResult<Field> {
+    let field = &input_fields[0];
+    let dtype = multivariate_float_dtype(field.name(), field.dtype())?;
+    if let (DataType::Array(_, dim), Some(EvalPoint::Coordinates(point))) =
+        (field.dtype(), kwargs.eval_points.first())
+    {
+        polars_ensure!(
+            point.len() == *dim,
+            ComputeError: "Expected `eval_points` to have {} coordinates, got: {}", dim, point.len()
+        );
+    }
+    Ok(Field::new(field.name().clone(), dtype))
+}
+
+/// Returns the float type of the densities for multivariate points of type `dtype`, which must be an `Array` of
+/// numeric or temporal values or a `Struct` of numeric or temporal fields.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument, used in the error message.
+/// * `dtype` - The data type of the argument.
+///
+/// # Returns
+///
+/// A result containing the float type that the densities are returned in.
+fn multivariate_float_dtype(name: &str, dtype: &DataType) -> PolarsResult<DataType> {
+    match dtype {
+        DataType::Array(inner, _) => float_dtype(name, inner),
+        DataType::Struct(fields) => joint_float_dtype(fields),
+        _ => polars_bail!(
+            ComputeError: "Expected `{}` to be an array or a struct of numeric or temporal values, got: {}", name, dtype
+        ),
+    }
+}
+
+/// Returns the float type of the densities for lists of values of type `dtype`, which must be a list of numeric or temporal values.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument, used in the error message.
+/// * `dtype` - The data type of the argument.
+///
+/// # Returns
+///
+/// A result containing the float type that the densities are returned in.
+fn list_float_dtype(name: &str, dtype: &DataType) -> PolarsResult<DataType> {
+    match dtype {
+        DataType::List(inner) => float_dtype(name, inner),
+        _ => polars_bail!(
+            ComputeError: "Expected `{}` to be a list of numeric or temporal values, got: {}", name, dtype
+        ),
+    }
+}
+
+/// Returns the nanosecond variant of a temporal type, or `None` if `dtype` is not temporal.
+///
+/// # Arguments
+///
+/// * `dtype` - The data type of the values.
+///
+/// # Returns
+///
+/// The temporal type whose physical representation counts nanoseconds.
+fn nanosecond_dtype(dtype: &DataType) -> Option<DataType> {
+    match dtype {
+        DataType::Date => Some(DataType::Datetime(TimeUnit::Nanoseconds, None)),
+        DataType::Datetime(_, tz) => Some(DataType::Datetime(TimeUnit::Nanoseconds, tz.clone())),
+        DataType::Duration(_) => Some(DataType::Duration(TimeUnit::Nanoseconds)),
+        DataType::Time => Some(DataType::Time),
+        _ => None,
+    }
+}
+
+/// Casts (lists of) numeric or temporal values to (lists of) `Float64`.
+///
+/// Temporal values are expressed in nanoseconds: since the Unix epoch for `Date` and `Datetime`, since midnight
+/// for `Time`, and as the length of a `Duration`.
+///
+/// # Arguments
+///
+/// * `s` - The series to cast.
+///
+/// # Returns
+///
+/// A result containing the series of `Float64` values.
+fn cast_to_f64(s: &Series) -> PolarsResult<Series> {
+    match s.dtype() {
+        DataType::List(inner) => match nanosecond_dtype(inner) {
+            Some(dtype) => s
+                .cast(&DataType::List(Box::new(dtype)))?
+                .to_physical_repr()
+                .cast(&DataType::List(Box::new(DataType::Float64))),
+            None => s.cast(&DataType::List(Box::new(DataType::Float64))),
+        },
+        dtype => match nanosecond_dtype(dtype) {
+            Some(dtype) => s.cast(&dtype)?.to_physical_repr().cast(&DataType::Float64),
+            None => s.cast(&DataType::Float64),
+        },
+    }
+}
+
+/// Casts the optional weights argument at `index` of the inputs to (lists of) `Float64`.
+///
+/// # Arguments
+///
+/// * `inputs` - A slice of input series.
+/// * `index` - The position of the weights among the inputs.
+/// * `list` - Whether the weights are expected to be lists of numeric values, like the sample points.
+///
+/// # Returns
+///
+/// A result containing the weights, or `None` if no weights were given.
+fn cast_weights(inputs: &[Series], index: usize, list: bool) -> PolarsResult<Option<Series>> {
+    let weights = match inputs.get(index) {
+        Some(weights) => weights,
+        None => return Ok(None),
+    };
+    let inner = match weights.dtype() {
+        DataType::List(inner) if list => inner.as_ref(),
+        dtype if !list => dtype,
+        dtype => polars_bail!(
+            ComputeError: "Expected `weights` to be a list of numeric values, got: {}", dtype
+        ),
+    };
+    polars_ensure!(
+        inner.is_numeric(),
+        ComputeError: "Expected `weights` to be numeric, got: {}", weights.dtype()
+    );
+    cast_to_f64(weights).map(Some)
+}
+
+/// Splits a series of multivariate points, given as `Array` or `Struct`, into one `Float64` series per dimension.
+///
+/// A null point yields null coordinates in every dimension.
+///
+/// # Arguments
+///
+/// * `s` - The series of multivariate points.
+///
+/// # Returns
+///
+/// A result containing the names of the dimensions (the struct fields, or the indices into the array) and their coordinates.
+fn split_coordinates(s: &Series) -> PolarsResult<(Vec<String>, Vec<Float64Chunked>)> {
+    let (names, columns): (Vec<String>, Vec<Float64Chunked>) = match s.dtype() {
+        DataType::Array(_, width) => {
+            let values = cast_to_f64(&s.array()?.get_inner())?;
+            let values = values.f64()?;
+            let names = (0..*width)
+                .map(|j| format!("{}[{}]", s.name(), j))
+                .collect();
+            let columns = (0..*width)
+                .map(|j| (0..s.len()).map(|i| values.get(i * width + j)).collect())
+                .collect();
+            (names, columns)
+        }
+        DataType::Struct(_) => {
+            let fields = s.struct_()?.fields_as_series();
+            let names = fields.iter().map(|f| f.name().to_string()).collect();
+            let columns = fields
+                .iter()
+                .map(|f| Ok(cast_to_f64(f)?.f64()?.clone()))
+                .collect::<PolarsResult<_>>()?;
+            (names, columns)
+        }
+        dtype => polars_bail!(
+            ComputeError: "Expected `{}` to be an array or a struct of numeric or temporal values, got: {}", s.name(), dtype
+        ),
+    };
+
+    let is_null = s.is_null();
+    let columns = columns
+        .iter()
+        .map(|ca| {
+            ca.into_iter()
+                .zip(&is_null)
+                .map(|(x, null)| if null == Some(true) { None } else { x })
+                .collect()
+        })
+        .collect();
+    Ok((names, columns))
+}
+
+/// Resolves the multivariate evaluation points into coordinates in the order of the dimensions.
+///
+/// # Arguments
+///
+/// * `eval_points` - The evaluation points, given by their coordinates or by the names of the struct fields.
+/// * `names` - The names of the dimensions.
+/// * `named` - Whether the dimensions are struct fields, which allows evaluation points given by name.
+///
+/// # Returns
+///
+/// A result containing the coordinates of the evaluation points, or an error if a point does not match the
+/// dimensions or contains non-finite values.
+fn resolve_eval_points(
+    eval_points: &[EvalPoint],
+    names: &[String],
+    named: bool,
+) -> PolarsResult<Vec<Vec<f64>>> {
+    eval_points
+        .iter()
+        .map(|point| {
+            let coordinates = match point {
+                EvalPoint::Coordinates(coordinates) => {
+                    polars_ensure!(
+                        coordinates.len() == names.len(),
+                        ComputeError: "Expected `eval_points` to have {} coordinates, got: {}", names.len(), coordinates.len()
+                    );
+                    coordinates.clone()
+                }
+                EvalPoint::Named(point) => {
+                    polars_ensure!(
+                        named,
+                        ComputeError: "Expected `eval_points` to be sequences of coordinates for array values"
+                    );
+                    if let Some(name) = point.keys().find(|name| !names.contains(name)) {
+                        polars_bail!(
+                            ComputeError: "Found unknown field `{}` in `eval_points`, expected: {:?}", name, names
+                        );
+                    }
+                    names
+                        .iter()
+                        .map(|name| match point.get(name) {
+                            Some(x) => Ok(*x),
+                            None => polars_bail!(
+                                ComputeError: "Expected `eval_points` to have field `{}`", name
+                            ),
+                        })
+                        .collect::<PolarsResult<Vec<_>>>()?
+                }
+            };
+            if let Some(value) = coordinates.iter().find(|x| !x.is_finite()) {
+                polars_bail!(
+                    ComputeError: "Expected `eval_points` to contain finite values, got: {}", value
+                );
+            }
+            Ok(coordinates)
+        })
+        .collect()
+}
+
+/// Validates that the coordinates of the multivariate evaluation points match the type of their dimension, see
+/// `check_temporal_arg`.
+///
+/// # Arguments
+///
+/// * `temporal` - Whether the coordinates of each dimension were given as temporal values, keyed by the index of
+///   the coordinate or by the name of the struct field.
+/// * `dtype` - The data type of the sample points, an `Array` or a `Struct`.
+/// * `names` - The names of the dimensions.
+///
+/// # Returns
+///
+/// An error if a dimension is given as temporal values for numeric coordinates, or vice versa.
+fn check_temporal_coordinates(
+    temporal: &HashMap<String, bool>,
+    dtype: &DataType,
+    names: &[String],
+) -> PolarsResult<()> {
+    let dtypes = match dtype {
+        DataType::Array(inner, width) => vec![inner.as_ref().clone(); *width],
+        DataType::Struct(fields) => fields.iter().map(|f| f.dtype().clone()).collect(),
+        _ => Vec::new(),
+    };
+    for (j, (name, dtype)) in names.iter().zip(&dtypes).enumerate() {
+        for key in [j.to_string(), name.clone()] {
+            if let Some(&temporal) = temporal.get(&key) {
+                check_temporal_arg("eval_points", temporal, name, dtype)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where the values in an error message come from.
+#[derive(Clone, Copy)]
+enum Location<'a> {
+    /// A list row, by its index.
+    Row(usize),
+    /// The group of an aggregation. The group keys are not passed to the plugin, so the group is identified by its
+    /// sample points, one column per dimension.
+    Group(&'a [&'a Float64Chunked]),
+    /// The keyword arguments, which are shared by all rows and groups.
+    Kwargs,
+}
+
+impl std::fmt::Display for Location<'_> {
+    /// Formats the location with a leading space, e.g. ` at row 3`, or as nothing for the keyword arguments.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Row(row) => write!(f, " at row {}", row),
+            Location::Group(columns) => {
+                let len = columns[0].len();
+                let points = (0..len.min(LOCATION_PREVIEW))
+                    .map(|i| {
+                        let coordinates = columns
+                            .iter()
+                            .map(|ca| ca.get(i).map_or("null".to_string(), |x| x.to_string()))
+                            .collect::<Vec<_>>();
+                        match coordinates.as_slice() {
+                            [x] => x.clone(),
+                            _ => format!("({})", coordinates.join(", ")),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let more = if len > LOCATION_PREVIEW { ", ..." } else { "" };
+                write!(
+                    f,
+                    " in the group of {} sample points [{}{}]",
+                    len,
+                    points.join(", "),
+                    more
+                )
+            }
+            Location::Kwargs => Ok(()),
+        }
+    }
+}
+
+/// Collects the (weighted) sample points of a group or list row according to the null and NaN policies.
+///
+/// A null or non-finite weight is treated like a null or non-finite sample point, i.e. the pair is dropped
+/// or makes the output null depending on the policy.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument, used in the error message.
+/// * `ca` - The values.
+/// * `weights` - The optional weights of the values, which must have the same length.
+/// * `options` - The estimator options holding the null and NaN policies.
+/// * `location` - Where the values come from, a list row or the group of an aggregation.
+///
+/// # Returns
+///
+/// A result containing the remaining sample, or `None` if the output should be null.
+fn collect_values(
+    name: &str,
+    ca: &Float64Chunked,
+    weights: Option<&Float64Chunked>,
+    options: &KdeOptions,
+    location: Location,
+) -> PolarsResult<Option<Sample>> {
+    if let Some(weights) = weights {
+        polars_ensure!(
+            weights.len() == ca.len(),
+            ComputeError: "Expected `weights` to have the same length as `{}`{}, got: {} and {}", name, location, weights.len(), ca.len()
+        );
+    }
+
+    let null_counts = [
+        (name, ca.null_count()),
+        ("weights", weights.map_or(0, |w| w.null_count())),
+    ];
+    for (arg, null_count) in null_counts {
+        if null_count > 0 {
+            match options.null_policy {
+                NullPolicy::Drop => {}
+                NullPolicy::Propagate => return Ok(None),
+                NullPolicy::Error => polars_bail!(
+                    ComputeError: "Found null values in `{}`{} with `null_policy='error'`", arg, location
+                ),
+            }
+        }
+    }
+
+    let mut pairs = match weights {
+        Some(weights) => ca
+            .into_iter()
+            .zip(weights)
+            .filter_map(|(x, w)| Some((x?, w?)))
+            .collect::<Vec<_>>(),
+        None => ca.into_iter().flatten().map(|x| (x, 1.0)).collect(),
+    };
+
+    let non_finite = [
+        (name, pairs.iter().map(|p| p.0).find(|x| !x.is_finite())),
+        (
+            "weights",
+            pairs.iter().map(|p| p.1).find(|w| !w.is_finite()),
+        ),
+    ];
+    for (arg, value) in non_finite {
+        if let Some(value) = value {
+            match options.nan_policy {
+                NanPolicy::Drop => {}
+                NanPolicy::Null => return Ok(None),
+                NanPolicy::Error => polars_bail!(
+                    ComputeError: "Found non-finite value {} in `{}`{} with `nan_policy='error'`", value, arg, location
+                ),
+            }
+        }
+    }
+    pairs.retain(|(x, w)| x.is_finite() && w.is_finite());
+
+    if let Some((_, w)) = pairs.iter().find(|(_, w)| *w < 0.0) {
+        polars_bail!(
+            ComputeError: "Expected `weights` to be non-negative, found {}{}", w, location
+        );
+    }
+    let (lower, upper) = options.support();
+    if let Some((x, _)) = pairs.iter().find(|(x, _)| *x < lower || *x > upper) {
+        polars_bail!(
+            ComputeError: "Expected `{}` to lie within the bounds `lower` and `upper`, found {}{}", name, x, location
+        );
+    }
+
+    let (points, values_weights): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+    Ok(Some(match weights {
+        Some(_) => Sample::new(points, values_weights),
+        None => Sample::unweighted(points),
+    }))
+}
+
+/// Collects the points of a multivariate sample, one coordinate per column, according to the null and NaN policies.
+///
+/// A point is dropped (or makes the output null, depending on the policy) if any of its coordinates is null or non-finite.
+///
+/// # Arguments
+///
+/// * `names` - The names of the arguments, used in the error message.
+/// * `columns` - The coordinates of the sample points, which must have the same length.
+/// * `null_policy` - The null policy.
+/// * `nan_policy` - The NaN policy.
+/// * `location` - Where the values come from, a list row or the group of an aggregation.
+///
+/// # Returns
+///
+/// A result containing the remaining sample, or `None` if the output should be null.
+fn collect_rows(
+    names: &[&str],
+    columns: &[&Float64Chunked],
+    null_policy: NullPolicy,
+    nan_policy: NanPolicy,
+    location: Location,
+) -> PolarsResult<Option<MultivariateSample>> {
+    let len = columns[0].len();
+    for (name, ca) in names.iter().zip(columns) {
+        polars_ensure!(
+            ca.len() == len,
+            ComputeError: "Expected `{}` to have the same length as `{}`{}, got: {} and {}", name, names[0], location, ca.len(), len
+        );
+        if ca.null_count() > 0 {
+            match null_policy {
+                NullPolicy::Drop => {}
+                NullPolicy::Propagate => return Ok(None),
+                NullPolicy::Error => polars_bail!(
+                    ComputeError: "Found null values in `{}`{} with `null_policy='error'`", name, location
+                ),
+            }
+        }
+    }
+
+    let mut iters = columns.iter().map(|ca| ca.into_iter()).collect::<Vec<_>>();
+    let mut points = Vec::with_capacity(len * columns.len());
+    for _ in 0..len {
+        let coordinates = iters
+            .iter_mut()
+            .map(|it| it.next().flatten())
+            .collect::<Vec<_>>();
+        let point = match coordinates.into_iter().collect::<Option<Vec<_>>>() {
+            Some(point) => point,
+            None => continue,
+        };
+        if let Some((name, value)) = names.iter().zip(&point).find(|(_, x)| !x.is_finite()) {
+            match nan_policy {
+                NanPolicy::Drop => continue,
+                NanPolicy::Null => return Ok(None),
+                NanPolicy::Error => polars_bail!(
+                    ComputeError: "Found non-finite value {} in `{}`{} with `nan_policy='error'`", value, name, location
+                ),
+            }
+        }
+        points.extend(point);
+    }
+    Ok(Some(MultivariateSample::new(columns.len(), points)))
+}
+
+/// Checks the evaluation points against the null and NaN policies.
+///
+/// Nulls and non-finite values are only rejected by the `error` policies, otherwise they yield null densities.
+///
+/// # Arguments
+///
+/// * `ca` - The evaluation points.
+/// * `options` - The estimator options holding the null and NaN policies.
+/// * `location` - Where the evaluation points come from, a list row or the keyword arguments.
+///
+/// # Returns
+///
+/// An error if the evaluation points violate an `error` policy.
+fn check_eval_points(
+    ca: &Float64Chunked,
+    options: &KdeOptions,
+    location: Location,
+) -> PolarsResult<()> {
+    polars_ensure!(
+        options.null_policy != NullPolicy::Error || ca.null_count() == 0,
+        ComputeError: "Found null values in `eval_points`{} with `null_policy='error'`", location
+    );
+    if options.nan_policy == NanPolicy::Error {
+        if let Some(value) = ca.into_iter().flatten().find(|x| !x.is_finite()) {
+            polars_bail!(
+                ComputeError: "Found non-finite value {} in `eval_points`{} with `nan_policy='error'`", value, location
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Handles a null list row according to the null policy.
+///
+/// # Arguments
+///
+/// * `name` - The name of the argument that is null, used in the error message.
+/// * `policy` - The null policy.
+/// * `row` - The index of the list row.
+///
+/// # Returns
+///
+/// A result containing a null output row.
+fn null_row<T>(name: &str, policy: NullPolicy, row: usize) -> PolarsResult<Option<T>> {
+    polars_ensure!(
+        policy != NullPolicy::Error,
+        ComputeError: "Found a null row in `{}`{} with `null_policy='error'`", name, Location::Row(row)
+    );
+    Ok(None)
+}
+
+/// Computes the kernel density estimation (KDE) for a given (weighted) sample and evaluation points.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - A vector of evaluation points.
+/// * `options` - The estimator options, e.g. the kernel and the bandwidth.
+///
+/// # Returns
+///
+/// A vector containing the KDE density estimates.
+fn compute_kde(sample: &Sample, eval_points: Vec<f64>, options: &KdeOptions) -> Vec<f64> {
+    let bandwidth = select_bandwidth(sample, options);
+    evaluate_kde(sample, &eval_points, bandwidth, options.estimate, options)
+}
 
-/// A struct for holding keyword arguments for KDE functions, specifically the evaluation points.
-#[derive(Deserialize)]
-struct KdeKwargs {
-    eval_points: Vec<f32>,
+/// Selects the bandwidth of a sample, including the `bw_adjust` multiplier.
+///
+/// On a periodic support, the bandwidth is selected for the sample cut open at its largest gap, see
+/// `periodic::unwrap_sample`.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `options` - The estimator options, e.g. the kernel and the bandwidth.
+///
+/// # Returns
+///
+/// The bandwidth, or `None` if the sample has at most one point, see `degenerate_kde`.
+fn select_bandwidth(sample: &Sample, options: &KdeOptions) -> Option<f64> {
+    if sample.len() <= 1 {
+        return None;
+    }
+    let unwrapped = options.period.map(|period| unwrap_sample(sample, period));
+    let sample = unwrapped.as_ref().unwrap_or(sample);
+    Some(
+        options
+            .bandwidth
+            .select(sample, options.kernel, options.cv_bounds)
+            * options.bw_adjust,
+    )
 }
 
-/// A helper function that returns the same output type as the input fields.
+/// Evaluates the estimated function with a given bandwidth using the method of the estimator options, with the
+/// boundary correction if the support is bounded, see `boundary::correct_boundary`, or wrapped around the period
+/// if it is periodic, see `periodic::wrap_sample`.
+///
+/// Outside of a bounded support, the density is zero and the CDF zero or one. On a periodic support, the CDF
+/// accumulates the density from zero, within the period of each evaluation point.
 ///
 /// # Arguments
 ///
-/// * `input_fields` - A slice of input fields.
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `bandwidth` - The bandwidth, or `None` if the sample has at most one point.
+/// * `estimate` - The estimated function.
+/// * `options` - The estimator options, e.g. the kernel and the method.
 ///
 /// # Returns
 ///
-/// A result containing the same output type as the input fields.
-fn same_output_type(input_fields: &[Field]) -> PolarsResult<Field> {
-    let field = &input_fields[0];
-    Ok(field.clone())
+/// A vector containing the KDE estimates.
+fn evaluate_kde(
+    sample: &Sample,
+    eval_points: &[f64],
+    bandwidth: Option<f64>,
+    estimate: Estimate,
+    options: &KdeOptions,
+) -> Vec<f64> {
+    let bandwidth = match bandwidth {
+        Some(bandwidth) => bandwidth,
+        None => return degenerate_kde(sample, eval_points, estimate),
+    };
+    if estimate == Estimate::Cdf {
+        return KdeCdf::new(sample, bandwidth, options).evaluate(eval_points);
+    }
+    let corrected = match correct_support(sample, bandwidth, options) {
+        Some(corrected) => corrected,
+        None => return evaluate_unbounded(sample, eval_points, bandwidth, estimate, options),
+    };
+
+    let (lower, upper) = options.support();
+    let eval_points = support_points(eval_points, options);
+    let scale = corrected.total_weight() / sample.total_weight();
+    let values = evaluate_unbounded(&corrected, &eval_points, bandwidth, estimate, options);
+    let inside = |x: f64| (lower..=upper).contains(&x);
+    eval_points
+        .iter()
+        .zip(values)
+        .map(|(&x, value)| match (inside(x), estimate) {
+            (true, Estimate::LogDensity) => value + scale.ln(),
+            (true, _) => value * scale,
+            (false, Estimate::LogDensity) => f64::NEG_INFINITY,
+            (false, _) => 0.0,
+        })
+        .collect()
+}
+
+/// Corrects the sample for a bounded support, see `boundary::correct_boundary`, or wraps it around the period of a
+/// periodic support, see `periodic::wrap_sample`.
+///
+/// # Returns
+///
+/// The corrected sample, or `None` if the support is unbounded.
+fn correct_support(sample: &Sample, bandwidth: f64, options: &KdeOptions) -> Option<Sample> {
+    if options.lower.is_none() && options.upper.is_none() && options.period.is_none() {
+        return None;
+    }
+    let (lower, upper) = options.support();
+    Some(match options.period {
+        Some(period) => wrap_sample(sample, period, options.kernel.radius() * bandwidth),
+        None => correct_boundary(
+            sample,
+            options.kernel,
+            bandwidth,
+            lower,
+            upper,
+            options.boundary,
+        ),
+    })
+}
+
+/// Reduces the evaluation points to the period starting at zero on a periodic support.
+fn support_points(eval_points: &[f64], options: &KdeOptions) -> Vec<f64> {
+    match options.period {
+        Some(period) => eval_points.iter().map(|x| x.rem_euclid(period)).collect(),
+        None => eval_points.to_vec(),
+    }
+}
+
+/// The CDF of the KDE of a sample with a given bandwidth, prepared to be evaluated repeatedly, e.g. when inverting
+/// it: the corrected sample of a bounded or periodic support, see `correct_support`, and the integrated grid of the
+/// `Fft` method are computed only once.
+///
+/// Outside of a bounded support, the CDF is zero or one. On a periodic support, it accumulates the density from
+/// zero, within the period of each evaluation point.
+struct KdeCdf<'a> {
+    sample: &'a Sample,
+    /// The corrected sample, or `None` if the support is unbounded.
+    corrected: Option<Sample>,
+    /// The binned CDF of the corrected sample, if the `Fft` method bins it.
+    binned: Option<BinnedCdf>,
+    bandwidth: f64,
+    options: &'a KdeOptions,
+    /// The CDF of the corrected sample at the lower end of a bounded support, or at zero on a periodic support.
+    base: f64,
+}
+
+impl<'a> KdeCdf<'a> {
+    fn new(sample: &'a Sample, bandwidth: f64, options: &'a KdeOptions) -> Self {
+        let corrected = correct_support(sample, bandwidth, options);
+        let binned = match options.method {
+            Method::Fft => BinnedCdf::new(
+                corrected.as_ref().unwrap_or(sample),
+                options.kernel,
+                bandwidth,
+            ),
+            Method::Exact | Method::Tree => None,
+        };
+        let mut cdf = KdeCdf {
+            sample,
+            corrected,
+            binned,
+            bandwidth,
+            options,
+            base: 0.0,
+        };
+        let origin = match options.period {
+            Some(_) => 0.0,
+            None => options.support().0,
+        };
+        if cdf.corrected.is_some() && origin.is_finite() {
+            cdf.base = cdf.evaluate_unbounded(&[origin])[0];
+        }
+        cdf
+    }
+
+    /// Evaluates the CDF of the corrected sample, ignoring the support.
+    fn evaluate_unbounded(&self, eval_points: &[f64]) -> Vec<f64> {
+        match &self.binned {
+            Some(binned) => binned.evaluate(eval_points),
+            None => evaluate_unbounded(
+                self.corrected.as_ref().unwrap_or(self.sample),
+                eval_points,
+                self.bandwidth,
+                Estimate::Cdf,
+                self.options,
+            ),
+        }
+    }
+
+    /// Evaluates the CDF at the evaluation points.
+    fn evaluate(&self, eval_points: &[f64]) -> Vec<f64> {
+        let corrected = match &self.corrected {
+            Some(corrected) => corrected,
+            None => return self.evaluate_unbounded(eval_points),
+        };
+        let (_, upper) = self.options.support();
+        let eval_points = support_points(eval_points, self.options);
+        let scale = corrected.total_weight() / self.sample.total_weight();
+        eval_points
+            .iter()
+            .zip(self.evaluate_unbounded(&eval_points))
+            .map(|(&x, cdf)| {
+                if x > upper {
+                    1.0
+                } else {
+                    ((cdf - self.base) * scale).clamp(0.0, 1.0)
+                }
+            })
+            .collect()
+    }
 }
 
-/// Computes the kernel density estimation (KDE) for given sample points and evaluation points.
+/// Evaluates the estimated function on an unbounded support with a given bandwidth using the method of the
+/// estimator options.
 ///
 /// # Arguments
 ///
-/// * `sample_points` - A vector of sample points.
-/// * `eval_points` - A vector of evaluation points.
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `bandwidth` - The bandwidth.
+/// * `estimate` - The estimated function.
+/// * `options` - The estimator options, e.g. the kernel and the method.
+///
+/// # Returns
+///
+/// A vector containing the KDE estimates.
+fn evaluate_unbounded(
+    sample: &Sample,
+    eval_points: &[f64],
+    bandwidth: f64,
+    estimate: Estimate,
+    options: &KdeOptions,
+) -> Vec<f64> {
+    let (kernel, rtol, atol) = (options.kernel, options.rtol, options.atol);
+    match (options.method, estimate) {
+        (Method::Exact, Estimate::Density) => exact_kde(sample, eval_points, kernel, bandwidth),
+        (Method::Exact, Estimate::Cdf) => exact_cdf(sample, eval_points, kernel, bandwidth),
+        // samples spanning too many bandwidths for the grid fall back to the exact method
+        (Method::Fft, Estimate::Density) => binned_kde(sample, eval_points, kernel, bandwidth)
+            .unwrap_or_else(|| exact_kde(sample, eval_points, kernel, bandwidth)),
+        (Method::Fft, Estimate::Cdf) => binned_cdf(sample, eval_points, kernel, bandwidth)
+            .unwrap_or_else(|| exact_cdf(sample, eval_points, kernel, bandwidth)),
+        (Method::Tree, Estimate::Density) => {
+            tree_kde(sample, eval_points, kernel, bandwidth, rtol, atol)
+        }
+        (Method::Tree, Estimate::Cdf) => {
+            tree_cdf(sample, eval_points, kernel, bandwidth, rtol, atol)
+        }
+        (Method::Exact, Estimate::LogDensity) => {
+            exact_log_kde(sample, eval_points, kernel, bandwidth)
+        }
+        (Method::Fft | Method::Tree, Estimate::LogDensity) => {
+            let densities =
+                evaluate_unbounded(sample, eval_points, bandwidth, Estimate::Density, options);
+            let floor = LOG_DENSITY_FLOOR / bandwidth;
+            let tails = eval_points
+                .iter()
+                .zip(&densities)
+                .filter(|(_, &density)| density < floor)
+                .map(|(&x, _)| x)
+                .collect::<Vec<_>>();
+            let mut tails = exact_log_kde(sample, &tails, kernel, bandwidth).into_iter();
+            densities
+                .into_iter()
+                .map(|density| {
+                    if density < floor {
+                        tails.next().unwrap_or(f64::NEG_INFINITY)
+                    } else {
+                        density.ln()
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Evaluates the estimated function of a sample with at most one point, which has no bandwidth.
+///
+/// The density is taken to be zero, and its logarithm negative infinity. The CDF is that of a point mass at the
+/// single sample point, and zero for an empty sample.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `estimate` - The estimated function.
+///
+/// # Returns
+///
+/// A vector containing the KDE estimates.
+fn degenerate_kde(sample: &Sample, eval_points: &[f64], estimate: Estimate) -> Vec<f64> {
+    match (estimate, sample.points.first()) {
+        (Estimate::Cdf, Some(&point)) => eval_points
+            .iter()
+            .map(|&x| if x >= point { 1.0 } else { 0.0 })
+            .collect(),
+        (Estimate::LogDensity, _) => vec![f64::NEG_INFINITY; eval_points.len()],
+        _ => vec![0.0; eval_points.len()],
+    }
+}
+
+/// Computes the KDE by summing the kernel contributions of all sample points at every evaluation point.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `kernel` - The kernel function.
+/// * `bandwidth` - The bandwidth.
 ///
 /// # Returns
 ///
 /// A vector containing the KDE density estimates.
-fn compute_kde(sample_points: Vec<f32>, eval_points: Vec<f32>) -> Vec<f32> {
-    if sample_points.len() <= 1 {
-        return vec![0.0; eval_points.len()];
+fn exact_kde(sample: &Sample, eval_points: &[f64], kernel: Kernel, bandwidth: f64) -> Vec<f64> {
+    let norm = 1.0 / (sample.total_weight() * bandwidth);
+
+    eval_points
+        .iter()
+        .map(|&x| {
+            let density: f64 = sample
+                .iter()
+                .map(|(xi, wi)| wi * kernel.pdf((x - xi) / bandwidth))
+                .sum();
+            density * norm
+        })
+        .collect()
+}
+
+/// Computes the CDF of the KDE by summing the integrated kernel contributions of all sample points at every
+/// evaluation point.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `kernel` - The kernel function.
+/// * `bandwidth` - The bandwidth.
+///
+/// # Returns
+///
+/// A vector containing the KDE CDF estimates.
+fn exact_cdf(sample: &Sample, eval_points: &[f64], kernel: Kernel, bandwidth: f64) -> Vec<f64> {
+    let norm = 1.0 / sample.total_weight();
+
+    eval_points
+        .iter()
+        .map(|&x| {
+            let cdf: f64 = sample
+                .iter()
+                .map(|(xi, wi)| wi * kernel.cdf((x - xi) / bandwidth))
+                .sum();
+            cdf * norm
+        })
+        .collect()
+}
+
+/// Computes the log-density of the KDE with a log-sum-exp over the kernel contributions of all sample points at every
+/// evaluation point, which stays finite where the density itself underflows.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `kernel` - The kernel function.
+/// * `bandwidth` - The bandwidth.
+///
+/// # Returns
+///
+/// A vector containing the KDE log-density estimates, which are negative infinity outside of a compact support.
+fn exact_log_kde(sample: &Sample, eval_points: &[f64], kernel: Kernel, bandwidth: f64) -> Vec<f64> {
+    let log_norm = (sample.total_weight() * bandwidth).ln();
+
+    eval_points
+        .iter()
+        .map(|&x| {
+            // streaming log-sum-exp: `sum` holds the sum of `exp(term - max)`
+            let (mut max, mut sum) = (f64::NEG_INFINITY, 0.0);
+            for (xi, wi) in sample.iter() {
+                let term = wi.ln() + kernel.log_pdf((x - xi) / bandwidth);
+                if term == f64::NEG_INFINITY {
+                    continue;
+                }
+                if term > max {
+                    sum = sum * (max - term).exp() + 1.0;
+                    max = term;
+                } else {
+                    sum += (term - max).exp();
+                }
+            }
+            max + sum.ln() - log_norm
+        })
+        .collect()
+}
+
+/// Computes the KDE at evaluation points that may contain nulls or non-finite values, which yield null densities.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `eval_points` - The evaluation points.
+/// * `options` - The estimator options.
+///
+/// # Returns
+///
+/// A chunked array containing the KDE density estimates.
+fn compute_kde_nullable(
+    sample: &Sample,
+    eval_points: &Float64Chunked,
+    options: &KdeOptions,
+) -> Float64Chunked {
+    let evals = eval_points
+        .into_iter()
+        .flatten()
+        .filter(|x| x.is_finite())
+        .collect::<Vec<_>>();
+    let mut densities = compute_kde(sample, evals, options).into_iter();
+
+    eval_points
+        .into_iter()
+        .map(|x| x.filter(|x| x.is_finite()).and_then(|_| densities.next()))
+        .collect()
+}
+
+/// Returns the range of a generated grid for a sample: the sample range, padded by `cut` bandwidths on both sides
+/// and clipped to the support, or one period starting at zero on a periodic support.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points, of which there must be at least one.
+/// * `bandwidth` - The bandwidth of the sample, or `None` if it has a single point.
+/// * `cut` - The padding in bandwidths.
+/// * `options` - The estimator options holding the bounds of the support.
+///
+/// # Returns
+///
+/// The lower and upper end of the grid, which stays within the day for `Time` samples.
+fn grid_range(
+    sample: &Sample,
+    bandwidth: Option<f64>,
+    cut: f64,
+    options: &KdeOptions,
+    input_dtype: &DataType,
+) -> (f64, f64) {
+    let (lo, hi) = match options.period {
+        Some(period) => (0.0, period),
+        None => {
+            let (min, max) = sample.range();
+            let (lower, upper) = options.support();
+            let padding = cut * bandwidth.unwrap_or(0.0);
+            ((min - padding).max(lower), (max + padding).min(upper))
+        }
+    };
+    match input_dtype {
+        DataType::Time => (lo.max(0.0), hi.min(NS_PER_DAY - 1.0)),
+        _ => (lo, hi),
+    }
+}
+
+/// Returns `n` evenly spaced points from `lo` to `hi`, or the midpoint if `n` is one.
+fn linspace(lo: f64, hi: f64, n: usize) -> Vec<f64> {
+    if n == 1 {
+        return vec![(lo + hi) / 2.0];
+    }
+    let step = (hi - lo) / (n - 1) as f64;
+    (0..n).map(|i| lo + step * i as f64).collect()
+}
+
+/// Evaluates the KDE on a generated grid and returns the grid next to the densities.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights.
+/// * `bandwidth` - The bandwidth of the sample, or `None` if it has at most one point.
+/// * `range` - The lower and upper end of the grid.
+/// * `grid` - The options of the generated evaluation points.
+/// * `options` - The estimator options.
+/// * `input_dtype` - The type of the sample points, which the grid points are cast back to if temporal, see
+///   `grid_points_dtype`.
+/// * `dtype` - The float type of the densities.
+///
+/// # Returns
+///
+/// A result containing the series of grid points and the series of their densities.
+fn grid_kde(
+    sample: &Sample,
+    bandwidth: Option<f64>,
+    (lo, hi): (f64, f64),
+    grid: &GridOptions,
+    options: &KdeOptions,
+    input_dtype: &DataType,
+    dtype: &DataType,
+) -> PolarsResult<(Series, Series)> {
+    let points = linspace(lo, hi, grid.n_points);
+    let densities = evaluate_kde(sample, &points, bandwidth, options.estimate, options);
+
+    let x = points_series(
+        &Float64Chunked::from_vec(PlSmallStr::EMPTY, points),
+        &grid_points_dtype(input_dtype),
+        dtype,
+    )?;
+    let density = Float64Chunked::from_vec(PlSmallStr::EMPTY, densities).into_series();
+    Ok((x, density))
+}
+
+/// Returns the type that generated grid points of samples of type `dtype` are cast back to.
+///
+/// Grid points of `Date` samples fall between days, so they are returned as `Datetime` instead.
+fn grid_points_dtype(dtype: &DataType) -> DataType {
+    match dtype {
+        DataType::Date => DataType::Datetime(TimeUnit::Nanoseconds, None),
+        _ => dtype.clone(),
+    }
+}
+
+/// Converts evaluation points to the type they are returned in.
+///
+/// Points of temporal samples are rounded to whole nanoseconds and cast back to the type of the sample points,
+/// all other points are cast to the float type of the densities.
+///
+/// # Arguments
+///
+/// * `points` - The evaluation points, as returned by `cast_to_f64`.
+/// * `input_dtype` - The type of the sample points.
+/// * `dtype` - The float type of the densities.
+///
+/// # Returns
+///
+/// A result containing the series of evaluation points.
+fn points_series(
+    points: &Float64Chunked,
+    input_dtype: &DataType,
+    dtype: &DataType,
+) -> PolarsResult<Series> {
+    match nanosecond_dtype(input_dtype) {
+        Some(ns_dtype) => points
+            .into_iter()
+            .map(|x| x.map(|x| x.round() as i64))
+            .collect::<Int64Chunked>()
+            .into_series()
+            .cast(&ns_dtype)?
+            .cast(input_dtype),
+        None => points.clone().into_series().cast(dtype),
     }
+}
+
+/// Combines evaluation points and their estimates into a struct series with the fields `x` and the name of the
+/// estimated function.
+fn points_struct(x: Series, values: Series, estimate: Estimate) -> PolarsResult<Series> {
+    Ok(DataFrame::new(vec![
+        x.with_name("x".into()),
+        values.with_name(estimate.name().into()),
+    ])?
+    .into_struct(PlSmallStr::EMPTY)
+    .into_series())
+}
+
+/// Lays out the evaluation points and densities of each list row in the given output layout.
+///
+/// # Arguments
+///
+/// * `rows` - The evaluation points and densities of each list row, or `None` for null rows.
+/// * `output` - The output layout.
+/// * `estimate` - The estimated function.
+/// * `out_dtype` - The type of the output, see `kde_dtype`.
+///
+/// # Returns
+///
+/// A result containing the series with one row per list row.
+fn list_output(
+    rows: Vec<Option<(Series, Series)>>,
+    output: KdeOutput,
+    estimate: Estimate,
+    out_dtype: &DataType,
+) -> PolarsResult<Series> {
+    let out = match output {
+        KdeOutput::Density | KdeOutput::Array => rows
+            .into_iter()
+            .map(|row| row.map(|(_, density)| density))
+            .collect::<ListChunked>()
+            .into_series(),
+        KdeOutput::Points => rows
+            .into_iter()
+            .map(|row| {
+                row.map(|(x, density)| points_struct(x, density, estimate))
+                    .transpose()
+            })
+            .collect::<PolarsResult<ListChunked>>()?
+            .into_series(),
+        KdeOutput::Columns => {
+            let (x, density): (Vec<_>, Vec<_>) = rows
+                .into_iter()
+                .map(|row| match row {
+                    Some((x, density)) => (Some(x), Some(density)),
+                    None => (None, None),
+                })
+                .unzip();
+            points_struct(
+                x.into_iter().collect::<ListChunked>().into_series(),
+                density.into_iter().collect::<ListChunked>().into_series(),
+                estimate,
+            )?
+        }
+    };
+    out.cast(out_dtype)
+}
 
-    let kde = KernelDensityEstimator::new(sample_points, Silverman, Normal);
-    kde.pdf(&eval_points)
+/// Lays out the evaluation points and densities of a group in the given output layout.
+///
+/// # Arguments
+///
+/// * `group` - The evaluation points and densities of the group, or `None` if its density is null.
+/// * `output` - The output layout.
+/// * `estimate` - The estimated function.
+/// * `len` - The number of evaluation points.
+/// * `out_dtype` - The type of the output, see `kde_dtype`.
+///
+/// # Returns
+///
+/// A result containing the series with one row per evaluation point for the `Points` layout, or a single row for the
+/// other layouts.
+fn group_output(
+    group: Option<(Series, Series)>,
+    output: KdeOutput,
+    estimate: Estimate,
+    len: usize,
+    out_dtype: &DataType,
+) -> PolarsResult<Series> {
+    let out = match (group, output) {
+        (None, KdeOutput::Points) => Series::full_null(PlSmallStr::EMPTY, len, out_dtype),
+        (None, _) => Series::full_null(PlSmallStr::EMPTY, 1, out_dtype),
+        (Some((x, density)), KdeOutput::Points) => points_struct(x, density, estimate)?,
+        (Some((x, density)), KdeOutput::Columns) => points_struct(
+            x.implode()?.into_series(),
+            density.implode()?.into_series(),
+            estimate,
+        )?,
+        (Some((_, density)), KdeOutput::Density | KdeOutput::Array) => {
+            density.implode()?.into_series()
+        }
+    };
+    out.cast(out_dtype)
 }
 
 /// Applies KDE to a series of sample points and evaluation points, returning the resulting density estimates as a series.
@@ -82,98 +1973,772 @@ fn compute_kde(sample_points: Vec<f32>, eval_points: Vec<f32>) -> Vec<f32> {
 /// # Arguments
 ///
 /// * `inputs` - A slice of input series.
+/// * `kwargs` - A struct containing the output layout and the estimator options.
 ///
 /// # Returns
 ///
 /// A result containing the series with the KDE density estimates.
-#[polars_expr(output_type_func=same_output_type)]
-fn kde_dynamic_evals(inputs: &[Series]) -> PolarsResult<Series> {
-    let sample_points: &ListChunked = inputs[0].list()?;
-    let eval_points: &ListChunked = inputs[1].list()?;
+#[polars_expr(output_type_func_with_kwargs=kde_dynamic_output_type)]
+fn kde_dynamic_evals(inputs: &[Series], kwargs: DynamicKwargs) -> PolarsResult<Series> {
+    let input_dtype = match inputs[0].dtype() {
+        DataType::List(inner) => inner.as_ref().clone(),
+        dtype => polars_bail!(
+            ComputeError: "Expected `values` to be a list of numeric or temporal values, got: {}", dtype
+        ),
+    };
+    check_options(&kwargs.options, &input_dtype)?;
+    let dtype = float_dtype("values", &input_dtype)?;
+    list_float_dtype("eval_points", inputs[1].dtype())?;
+    if let DataType::List(eval_dtype) = inputs[1].dtype() {
+        check_temporal_arg(
+            "eval_points",
+            eval_dtype.is_temporal(),
+            "values",
+            &input_dtype,
+        )?;
+    }
+    let out_dtype = kde_dtype(
+        "values",
+        &input_dtype,
+        kwargs.output,
+        true,
+        None,
+        false,
+        kwargs.options.estimate,
+    )?;
 
-    polars_ensure!(
-        sample_points.dtype() == &DataType::List(Box::new(DataType::Float32)),
-        ComputeError: "Expected `values` to be of type `List(Float32)`, got: {}", sample_points.dtype()
-    );
+    let sample_points = cast_to_f64(&inputs[0])?;
+    let eval_points = cast_to_f64(&inputs[1])?;
+    let sample_points: &ListChunked = sample_points.list()?;
+    let eval_points: &ListChunked = eval_points.list()?;
 
     let (sample_points, eval_points) = align_chunks_binary(sample_points, eval_points);
 
-    let out: ListChunked = sample_points
+    let weights = cast_weights(inputs, 2, true)?;
+    let mut weights = weights
+        .as_ref()
+        .map(|w| w.list())
+        .transpose()?
+        .map(|w| w.amortized_iter());
+
+    let policy = kwargs.options.null_policy;
+
+    let rows = sample_points
         .amortized_iter()
         .zip(eval_points.amortized_iter())
-        .map(|(lhs, rhs)| {
-            let lhs = lhs.unwrap();
-            let rhs = rhs.unwrap();
+        .enumerate()
+        .map(|(row, (lhs, rhs))| {
+            let row_weights = weights.as_mut().map(|w| w.next().flatten());
+            let lhs = match lhs {
+                Some(lhs) => lhs,
+                None => return null_row("values", policy, row),
+            };
+            let rhs = match rhs {
+                Some(rhs) => rhs,
+                None => return null_row("eval_points", policy, row),
+            };
+            let row_weights = match row_weights {
+                Some(Some(w)) => Some(w),
+                Some(None) => return null_row("weights", policy, row),
+                None => None,
+            };
 
-            let points_inner: &Float32Chunked = lhs.as_ref().f32().unwrap();
-            let eval_innter: &Float32Chunked = rhs.as_ref().f32().unwrap();
+            let points_inner: &Float64Chunked = lhs.as_ref().f64()?;
+            let eval_inner: &Float64Chunked = rhs.as_ref().f64()?;
+            let weights_inner = row_weights.as_ref().map(|w| w.as_ref().f64()).transpose()?;
 
-            let sample_points = points_inner.into_no_null_iter().collect::<Vec<_>>();
+            check_eval_points(eval_inner, &kwargs.options, Location::Row(row))?;
 
-            let eval_points = eval_innter.into_no_null_iter().collect::<Vec<_>>();
+            let sample = match collect_values(
+                "values",
+                points_inner,
+                weights_inner,
+                &kwargs.options,
+                Location::Row(row),
+            )? {
+                Some(sample) => sample,
+                None => return Ok(None),
+            };
 
-            let samples = compute_kde(sample_points, eval_points);
+            let samples = compute_kde_nullable(&sample, eval_inner, &kwargs.options);
 
-            Series::new(PlSmallStr::EMPTY, samples)
+            Ok(Some((
+                points_series(eval_inner, &input_dtype, &dtype)?,
+                samples.into_series(),
+            )))
         })
-        .collect();
+        .collect::<PolarsResult<Vec<_>>>()?;
 
-    Ok(out.into_series())
+    list_output(rows, kwargs.output, kwargs.options.estimate, &out_dtype)
 }
 
 /// Applies KDE to a series of sample points with evaluation points provided via keyword arguments, returning the resulting density estimates as a series.
 ///
+/// Without evaluation points, each row is evaluated on a generated grid. Depending on the output layout, the
+/// evaluation points are returned next to the densities.
+///
 /// # Arguments
 ///
 /// * `inputs` - A slice of input series.
-/// * `kwargs` - A struct containing the evaluation points.
+/// * `kwargs` - A struct containing the evaluation points (or the options to generate them), the output layout and
+///   estimator options.
 ///
 /// # Returns
 ///
 /// A result containing the series with the KDE density estimates.
-#[polars_expr(output_type_func=same_output_type)]
+#[polars_expr(output_type_func_with_kwargs=kde_static_output_type)]
 fn kde_static_evals(inputs: &[Series], kwargs: KdeKwargs) -> PolarsResult<Series> {
-    let ca: &ListChunked = inputs[0].list()?;
+    let input_dtype = match inputs[0].dtype() {
+        DataType::List(inner) => inner.as_ref().clone(),
+        dtype => polars_bail!(
+            ComputeError: "Expected `values` to be a list of numeric or temporal values, got: {}", dtype
+        ),
+    };
+    check_options(&kwargs.options, &input_dtype)?;
+    let dtype = float_dtype("values", &input_dtype)?;
+    let out_dtype = kde_dtype(
+        "values",
+        &input_dtype,
+        kwargs.output,
+        true,
+        Some(eval_len(&kwargs)),
+        kwargs.eval_points.is_none(),
+        kwargs.options.estimate,
+    )?;
+    let values = cast_to_f64(&inputs[0])?;
+    let ca: &ListChunked = values.list()?;
 
-    polars_ensure!(
-        ca.dtype() == &DataType::List(Box::new(DataType::Float32)),
-        ComputeError: "Expected `values` to be of type `List(Float32)`, got: {}", ca.dtype()
-    );
+    if kwargs.eval_points.is_some() {
+        check_temporal_arg(
+            "eval_points",
+            kwargs.temporal_eval_points,
+            "values",
+            &input_dtype,
+        )?;
+    }
+    let eval_points = kwargs.eval_points.map(|eval_points| {
+        Float64Chunked::from_iter_options(PlSmallStr::EMPTY, eval_points.into_iter())
+    });
+    match &eval_points {
+        Some(eval_points) => check_eval_points(eval_points, &kwargs.options, Location::Kwargs)?,
+        None => check_grid_options(&kwargs.grid)?,
+    }
+    let policy = kwargs.options.null_policy;
 
-    let eval_points = kwargs.eval_points;
+    let weights = cast_weights(inputs, 1, true)?;
+    let mut weights = weights
+        .as_ref()
+        .map(|w| w.list())
+        .transpose()?
+        .map(|w| w.amortized_iter());
 
-    let out: ListChunked = ca.apply_amortized(|s| {
-        let s = s.as_ref();
-        let points_inner = s.f32().unwrap();
+    let samples = ca
+        .amortized_iter()
+        .enumerate()
+        .map(|(row, s)| {
+            let row_weights = weights.as_mut().map(|w| w.next().flatten());
+            let s = match s {
+                Some(s) => s,
+                None => return null_row("values", policy, row),
+            };
+            let row_weights = match row_weights {
+                Some(Some(w)) => Some(w),
+                Some(None) => return null_row("weights", policy, row),
+                None => None,
+            };
+            let points_inner = s.as_ref().f64()?;
+            let weights_inner = row_weights.as_ref().map(|w| w.as_ref().f64()).transpose()?;
 
-        let sample_points = points_inner.into_no_null_iter().collect::<Vec<_>>();
+            collect_values(
+                "values",
+                points_inner,
+                weights_inner,
+                &kwargs.options,
+                Location::Row(row),
+            )
+        })
+        .collect::<PolarsResult<Vec<_>>>()?;
 
-        let samples = compute_kde(sample_points, eval_points.clone());
+    let rows: Vec<Option<(Series, Series)>> = match &eval_points {
+        Some(eval_points) => {
+            let x = points_series(eval_points, &input_dtype, &dtype)?;
+            samples
+                .iter()
+                .map(|sample| {
+                    sample.as_ref().map(|sample| {
+                        let density = compute_kde_nullable(sample, eval_points, &kwargs.options);
+                        (x.clone(), density.into_series())
+                    })
+                })
+                .collect()
+        }
+        None => {
+            let bandwidths = samples
+                .iter()
+                .map(|sample| {
+                    sample
+                        .as_ref()
+                        .and_then(|sample| select_bandwidth(sample, &kwargs.options))
+                })
+                .collect::<Vec<_>>();
+            let ranges = samples
+                .iter()
+                .zip(&bandwidths)
+                .map(|(sample, &bandwidth)| match sample {
+                    Some(sample) if !sample.is_empty() => Some(grid_range(
+                        sample,
+                        bandwidth,
+                        kwargs.grid.cut,
+                        &kwargs.options,
+                        &input_dtype,
+                    )),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let global = ranges
+                .iter()
+                .flatten()
+                .copied()
+                .reduce(|(lo, hi), (l, h)| (lo.min(l), hi.max(h)));
 
-        Series::new(PlSmallStr::EMPTY, samples)
-    });
+            samples
+                .iter()
+                .zip(bandwidths)
+                .zip(ranges)
+                .map(|((sample, bandwidth), range)| {
+                    let range = match kwargs.grid.grid {
+                        GridRange::Local => range,
+                        GridRange::Global => global,
+                    };
+                    match (sample, range) {
+                        (Some(sample), Some(range)) => grid_kde(
+                            sample,
+                            bandwidth,
+                            range,
+                            &kwargs.grid,
+                            &kwargs.options,
+                            &input_dtype,
+                            &dtype,
+                        )
+                        .map(Some),
+                        _ => Ok(None),
+                    }
+                })
+                .collect::<PolarsResult<_>>()?
+        }
+    };
 
-    Ok(out.into_series())
+    list_output(rows, kwargs.output, kwargs.options.estimate, &out_dtype)
 }
 
 /// Aggregates KDE results for a series of sample points with evaluation points provided via keyword arguments, returning the resulting density estimates as a series.
 ///
+/// Without evaluation points, the group is evaluated on a generated grid. Depending on the output layout, the
+/// evaluation points are returned next to the densities.
+///
 /// # Arguments
 ///
 /// * `inputs` - A slice of input series.
-/// * `kwargs` - A struct containing the evaluation points.
+/// * `kwargs` - A struct containing the evaluation points (or the options to generate them), the output layout and
+///   estimator options.
 ///
 /// # Returns
 ///
 /// A result containing the series with the KDE density estimates.
-#[polars_expr(output_type_func=same_output_type)]
+#[polars_expr(output_type_func_with_kwargs=kde_agg_output_type)]
 fn kde_agg(inputs: &[Series], kwargs: KdeKwargs) -> PolarsResult<Series> {
-    let values = &inputs[0].f32()?;
-    let eval_points = kwargs.eval_points;
+    let input_dtype = inputs[0].dtype().clone();
+    check_options(&kwargs.options, &input_dtype)?;
+    let dtype = float_dtype("values", &input_dtype)?;
+    let values = cast_to_f64(&inputs[0])?;
+    let values = values.f64()?;
+    let weights = cast_weights(inputs, 1, false)?;
+    let weights = weights.as_ref().map(|w| w.f64()).transpose()?;
+
+    let out_dtype = kde_dtype(
+        "values",
+        &input_dtype,
+        kwargs.output,
+        false,
+        Some(eval_len(&kwargs)),
+        kwargs.eval_points.is_none(),
+        kwargs.options.estimate,
+    )?;
+
+    if kwargs.eval_points.is_some() {
+        check_temporal_arg(
+            "eval_points",
+            kwargs.temporal_eval_points,
+            "values",
+            &input_dtype,
+        )?;
+    }
+    let eval_points = kwargs.eval_points.map(|eval_points| {
+        Float64Chunked::from_iter_options(PlSmallStr::EMPTY, eval_points.into_iter())
+    });
+    match &eval_points {
+        Some(eval_points) => check_eval_points(eval_points, &kwargs.options, Location::Kwargs)?,
+        None => {
+            check_grid_options(&kwargs.grid)?;
+            polars_ensure!(
+                kwargs.grid.grid == GridRange::Local,
+                ComputeError: "A `grid='global'` spanning all groups is not supported in aggregations, use `kde_static_evals` on the aggregated lists instead"
+            );
+        }
+    }
+
+    let sample = collect_values(
+        "values",
+        values,
+        weights,
+        &kwargs.options,
+        Location::Group(&[values]),
+    )?;
+
+    let (group, len) = match &eval_points {
+        Some(eval_points) => {
+            let group = match sample {
+                Some(sample) => {
+                    let density = compute_kde_nullable(&sample, eval_points, &kwargs.options);
+                    Some((
+                        points_series(eval_points, &input_dtype, &dtype)?,
+                        density.into_series(),
+                    ))
+                }
+                None => None,
+            };
+            (group, eval_points.len())
+        }
+        None => {
+            let group = match sample {
+                Some(sample) if !sample.is_empty() => {
+                    let bandwidth = select_bandwidth(&sample, &kwargs.options);
+                    let range = grid_range(
+                        &sample,
+                        bandwidth,
+                        kwargs.grid.cut,
+                        &kwargs.options,
+                        &input_dtype,
+                    );
+                    Some(grid_kde(
+                        &sample,
+                        bandwidth,
+                        range,
+                        &kwargs.grid,
+                        &kwargs.options,
+                        &input_dtype,
+                        &dtype,
+                    )?)
+                }
+                _ => None,
+            };
+            (group, kwargs.grid.n_points)
+        }
+    };
+
+    group_output(
+        group,
+        kwargs.output,
+        kwargs.options.estimate,
+        len,
+        &out_dtype,
+    )
+}
+
+/// Computes the quantiles of the KDE by inverting its CDF with bisection, for all levels at once.
+///
+/// The bisection starts from the sample range extended by the kernel radius and clipped to the support, so that
+/// the levels 0 and 1 yield its lower and upper end. On a periodic support, it starts from one period starting at
+/// zero. The CDF is prepared once for all bisection steps, see `KdeCdf`.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points and their weights, of which there must be at least one.
+/// * `levels` - The quantile levels.
+/// * `options` - The estimator options, e.g. the kernel and the method.
+///
+/// # Returns
+///
+/// A vector containing the quantiles, or the single sample point for every level if there is no bandwidth.
+fn compute_quantiles(sample: &Sample, levels: &[f64], options: &KdeOptions) -> Vec<f64> {
+    let bandwidth = match select_bandwidth(sample, options) {
+        Some(bandwidth) => bandwidth,
+        None => return vec![sample.points[0]; levels.len()],
+    };
+    let (start, end) = match options.period {
+        Some(period) => (0.0, period),
+        None => {
+            let (min, max) = sample.range();
+            let (lower, upper) = options.support();
+            let padding = options.kernel.radius() * bandwidth;
+            ((min - padding).max(lower), (max + padding).min(upper))
+        }
+    };
+    let kde_cdf = KdeCdf::new(sample, bandwidth, options);
+    let mut lo = vec![start; levels.len()];
+    let mut hi = vec![end; levels.len()];
+
+    for _ in 0..QUANTILE_BISECTIONS {
+        let mid = lo
+            .iter()
+            .zip(&hi)
+            .map(|(l, h)| 0.5 * (l + h))
+            .collect::<Vec<_>>();
+        let cdf = kde_cdf.evaluate(&mid);
+        for (i, (&m, &c)) in mid.iter().zip(&cdf).enumerate() {
+            if c < levels[i] {
+                lo[i] = m;
+            } else {
+                hi[i] = m;
+            }
+        }
+    }
+    lo.iter().zip(&hi).map(|(l, h)| 0.5 * (l + h)).collect()
+}
+
+/// Aggregates the quantiles of the KDE of a series of sample points, with the quantile levels provided via keyword
+/// arguments.
+///
+/// # Arguments
+///
+/// * `inputs` - A slice of input series.
+/// * `kwargs` - A struct containing the quantile levels and estimator options.
+///
+/// # Returns
+///
+/// A result containing the series with one quantile per level, which are null if the sample is empty or null.
+#[polars_expr(output_type_func=value_output_type)]
+fn kde_quantile_agg(inputs: &[Series], kwargs: KdeQuantileKwargs) -> PolarsResult<Series> {
+    check_quantiles(&kwargs.quantiles)?;
+
+    let input_dtype = inputs[0].dtype().clone();
+    check_options(&kwargs.options, &input_dtype)?;
+    let dtype = float_dtype("values", &input_dtype)?;
+    let values = cast_to_f64(&inputs[0])?;
+    let values = values.f64()?;
+    let weights = cast_weights(inputs, 1, false)?;
+    let weights = weights.as_ref().map(|w| w.f64()).transpose()?;
+
+    let quantiles = match collect_values(
+        "values",
+        values,
+        weights,
+        &kwargs.options,
+        Location::Group(&[values]),
+    )? {
+        Some(sample) if !sample.is_empty() => Float64Chunked::from_vec(
+            PlSmallStr::EMPTY,
+            compute_quantiles(&sample, &kwargs.quantiles, &kwargs.options),
+        ),
+        _ => Float64Chunked::full_null(PlSmallStr::EMPTY, kwargs.quantiles.len()),
+    };
+    points_series(&quantiles, &input_dtype, &dtype)
+}
+
+/// Draws synthetic samples from the KDE of a series of sample points, with the number of draws and the seed
+/// provided via keyword arguments.
+///
+/// Each draw picks a sample point with probability proportional to its weight and adds kernel noise scaled by the
+/// bandwidth, confined to a bounded or periodic support like the corrected estimate, see `bootstrap::draw`.
+///
+/// # Arguments
+///
+/// * `inputs` - A slice of input series.
+/// * `kwargs` - A struct containing the number of draws, the seed and estimator options.
+///
+/// # Returns
+///
+/// A result containing the series of `n` draws, which are null if the sample is empty or null.
+#[polars_expr(output_type_func=value_output_type)]
+fn kde_sample_agg(inputs: &[Series], kwargs: KdeSampleKwargs) -> PolarsResult<Series> {
+    let input_dtype = inputs[0].dtype().clone();
+    check_options(&kwargs.options, &input_dtype)?;
+    let dtype = float_dtype("values", &input_dtype)?;
+    let values = cast_to_f64(&inputs[0])?;
+    let values = values.f64()?;
+    let weights = cast_weights(inputs, 1, false)?;
+    let weights = weights.as_ref().map(|w| w.f64()).transpose()?;
+    let seed = kwargs.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+    });
+
+    let draws = match collect_values(
+        "values",
+        values,
+        weights,
+        &kwargs.options,
+        Location::Group(&[values]),
+    )? {
+        Some(sample) if !sample.is_empty() => {
+            let bandwidth = select_bandwidth(&sample, &kwargs.options);
+            let support = match (kwargs.options.period, kwargs.options.support()) {
+                (Some(period), _) => Support::Periodic(period),
+                (None, (lower, upper)) if lower.is_finite() || upper.is_finite() => {
+                    Support::Bounded(lower, upper, kwargs.options.boundary)
+                }
+                _ => Support::Unbounded,
+            };
+            Float64Chunked::from_vec(
+                PlSmallStr::EMPTY,
+                draw(
+                    &sample,
+                    kwargs.n,
+                    kwargs.options.kernel,
+                    bandwidth,
+                    support,
+                    seed,
+                ),
+            )
+        }
+        _ => Float64Chunked::full_null(PlSmallStr::EMPTY, kwargs.n),
+    };
+    points_series(&draws, &input_dtype, &dtype)
+}
+
+/// Aggregates the local maxima of the KDE of a series of sample points, found by mean shift and filtered by their
+/// prominence, see `modes::find_modes`.
+///
+/// # Arguments
+///
+/// * `inputs` - A slice of input series.
+/// * `kwargs` - A struct containing the minimum prominence and estimator options.
+///
+/// # Returns
+///
+/// A result containing the series with one struct of `x`, `density` and `prominence` per mode, in increasing
+/// order of `x`. An empty or null sample has no modes, and a single sample point, which has no bandwidth, is a
+/// mode of density zero, see `degenerate_kde`.
+#[polars_expr(output_type_func=kde_modes_output_type)]
+fn kde_modes_agg(inputs: &[Series], kwargs: KdeModesKwargs) -> PolarsResult<Series> {
+    check_modes(&kwargs)?;
+
+    let input_dtype = inputs[0].dtype().clone();
+    check_options(&kwargs.options, &input_dtype)?;
+    let dtype = float_dtype("values", &input_dtype)?;
+    let values = cast_to_f64(&inputs[0])?;
+    let values = values.f64()?;
+    let weights = cast_weights(inputs, 1, false)?;
+    let weights = weights.as_ref().map(|w| w.f64()).transpose()?;
+
+    let mut modes = (Vec::new(), Vec::new(), Vec::new());
+    if let Some(sample) = collect_values(
+        "values",
+        values,
+        weights,
+        &kwargs.options,
+        Location::Group(&[values]),
+    )? {
+        match select_bandwidth(&sample, &kwargs.options) {
+            Some(bandwidth) => {
+                for mode in find_modes(
+                    &sample,
+                    kwargs.options.kernel,
+                    bandwidth,
+                    kwargs.min_prominence,
+                ) {
+                    modes.0.push(mode.x);
+                    modes.1.push(mode.density);
+                    modes.2.push(mode.prominence);
+                }
+            }
+            None => {
+                if let Some(&point) = sample.points.first() {
+                    modes = (vec![point], vec![0.0], vec![0.0]);
+                }
+            }
+        }
+    }
+    let (x, density, prominence) = modes;
+
+    let x = points_series(
+        &Float64Chunked::from_vec(PlSmallStr::EMPTY, x),
+        &input_dtype,
+        &dtype,
+    )?;
+    Ok(DataFrame::new(vec![
+        x.with_name("x".into()),
+        Float64Chunked::from_vec("density".into(), density)
+            .into_series()
+            .cast(&dtype)?,
+        Float64Chunked::from_vec("prominence".into(), prominence)
+            .into_series()
+            .cast(&dtype)?,
+    ])?
+    .into_struct(PlSmallStr::EMPTY)
+    .into_series())
+}
+
+/// Computes the multivariate KDE of a sample at the given points, stored row-major.
+///
+/// # Arguments
+///
+/// * `sample` - The sample points.
+/// * `eval_points` - The evaluation points, with the dimension of the sample.
+/// * `options` - The multivariate estimator options.
+///
+/// # Returns
+///
+/// A vector containing the KDE density estimates, which are zero if the bandwidth matrix is singular.
+fn compute_multivariate_kde(
+    sample: &MultivariateSample,
+    eval_points: &[Vec<f64>],
+    options: &MultivariateOptions,
+) -> Vec<f64> {
+    match MultivariateKde::new(
+        sample,
+        options.bandwidth,
+        options.bandwidth_matrix,
+        options.bw_adjust,
+    ) {
+        Some(kde) => eval_points.iter().map(|x| kde.density(x)).collect(),
+        None => vec![0.0; eval_points.len()],
+    }
+}
+
+/// Lays out the densities of a grid of `nx` by `ny` points, stored row-major, according to the output layout.
+///
+/// # Arguments
+///
+/// * `densities` - The densities, or `None` if the output should be null.
+/// * `nx` - The number of points along the first axis.
+/// * `ny` - The number of points along the second axis.
+/// * `dtype` - The float type of the densities.
+/// * `output` - The output layout.
+///
+/// # Returns
+///
+/// A result containing the flattened densities, or a single nested `Array`.
+fn grid_series(
+    densities: Option<Vec<f64>>,
+    nx: usize,
+    ny: usize,
+    dtype: &DataType,
+    output: GridOutput,
+) -> PolarsResult<Series> {
+    let out_dtype = grid_dtype(dtype.clone(), nx, ny, output);
+    let densities = match densities {
+        Some(densities) => densities,
+        None => {
+            let len = match output {
+                GridOutput::List => nx * ny,
+                GridOutput::Array => 1,
+            };
+            return Ok(Series::full_null(PlSmallStr::EMPTY, len, &out_dtype));
+        }
+    };
+
+    match output {
+        GridOutput::List => Float64Chunked::from_vec(PlSmallStr::EMPTY, densities)
+            .into_series()
+            .cast(dtype),
+        GridOutput::Array => {
+            let rows: ListChunked = densities
+                .chunks(ny)
+                .map(|row| Some(Float64Chunked::from_slice(PlSmallStr::EMPTY, row).into_series()))
+                .collect();
+            let rows = rows
+                .into_series()
+                .cast(&DataType::Array(Box::new(dtype.clone()), ny))?;
+            rows.implode()?.into_series().cast(&out_dtype)
+        }
+    }
+}
+
+/// Aggregates a bivariate KDE of two series of sample points, evaluated on the grid spanned by the points
+/// provided via keyword arguments.
+///
+/// The density at `(grid_x[i], grid_y[j])` is stored at position `i * grid_y.len() + j`.
+///
+/// # Arguments
+///
+/// * `inputs` - A slice of input series, the x and y coordinates of the sample points.
+/// * `kwargs` - A struct containing the grid, its output layout and the estimator options.
+///
+/// # Returns
+///
+/// A result containing the series with the KDE density estimates.
+#[polars_expr(output_type_func_with_kwargs=kde2d_output_type)]
+fn kde2d_agg(inputs: &[Series], kwargs: Kde2dKwargs) -> PolarsResult<Series> {
+    check_multivariate_options(&kwargs.options)?;
+    check_grid_axis("grid_x", &kwargs.grid_x)?;
+    check_grid_axis("grid_y", &kwargs.grid_y)?;
+    for (arg, input) in [("grid_x", &inputs[0]), ("grid_y", &inputs[1])] {
+        if let Some(&temporal) = kwargs.temporal.get(arg) {
+            check_temporal_arg(arg, temporal, input.name(), input.dtype())?;
+        }
+    }
+
+    let dtype = joint_float_dtype(&[
+        Field::new("x".into(), inputs[0].dtype().clone()),
+        Field::new("y".into(), inputs[1].dtype().clone()),
+    ])?;
+    let x = cast_to_f64(&inputs[0])?;
+    let y = cast_to_f64(&inputs[1])?;
+
+    let (nx, ny) = (kwargs.grid_x.len(), kwargs.grid_y.len());
+    let columns = [x.f64()?, y.f64()?];
+    let sample = collect_rows(
+        &["x", "y"],
+        &columns,
+        kwargs.options.null_policy,
+        kwargs.options.nan_policy,
+        Location::Group(&columns),
+    )?;
+    let densities = sample.map(|sample| {
+        let grid = kwargs
+            .grid_x
+            .iter()
+            .flat_map(|&x| kwargs.grid_y.iter().map(move |&y| vec![x, y]))
+            .collect::<Vec<_>>();
+        compute_multivariate_kde(&sample, &grid, &kwargs.options)
+    });
+
+    grid_series(densities, nx, ny, &dtype, kwargs.output)
+}
+
+/// Aggregates a multivariate KDE of a series of sample points, each an `Array` row or a `Struct` of coordinates,
+/// with evaluation points provided via keyword arguments.
+///
+/// # Arguments
+///
+/// * `inputs` - A slice of input series.
+/// * `kwargs` - A struct containing the evaluation points and estimator options.
+///
+/// # Returns
+///
+/// A result containing the series with the KDE density estimates.
+#[polars_expr(output_type_func_with_kwargs=kde_nd_output_type)]
+fn kde_nd_agg(inputs: &[Series], kwargs: KdeNdKwargs) -> PolarsResult<Series> {
+    check_multivariate_options(&kwargs.options)?;
 
-    let sample_points = values.into_no_null_iter().collect::<Vec<_>>();
+    let dtype = multivariate_float_dtype("values", inputs[0].dtype())?;
+    let (names, columns) = split_coordinates(&inputs[0])?;
+    let named = matches!(inputs[0].dtype(), DataType::Struct(_));
+    let eval_points = resolve_eval_points(&kwargs.eval_points, &names, named)?;
+    check_temporal_coordinates(&kwargs.temporal, inputs[0].dtype(), &names)?;
 
-    let samples = compute_kde(sample_points, eval_points);
+    let columns = columns.iter().collect::<Vec<_>>();
+    let sample = collect_rows(
+        &names.iter().map(String::as_str).collect::<Vec<_>>(),
+        &columns,
+        kwargs.options.null_policy,
+        kwargs.options.nan_policy,
+        Location::Group(&columns),
+    )?;
 
-    Ok(Series::new(PlSmallStr::EMPTY, samples))
+    match sample {
+        Some(sample) => {
+            let densities = compute_multivariate_kde(&sample, &eval_points, &kwargs.options);
+            Float64Chunked::from_vec(PlSmallStr::EMPTY, densities)
+                .into_series()
+                .cast(&dtype)
+        }
+        None => Ok(Series::full_null(
+            PlSmallStr::EMPTY,
+            eval_points.len(),
+            &dtype,
+        )),
+    }
 }
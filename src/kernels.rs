@@ -0,0 +1,226 @@
+//! Kernel functions used by the density estimators.
+//!
+//! All kernels are expressed in standardised form, i.e. as a function of `u = (x - x_i) / h`.
+//! Kernels with compact support are zero outside of `[-1, 1]`.
+
+use libm::erfc;
+use serde::Deserialize;
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+use std::sync::OnceLock;
+
+/// Radius beyond which the Gaussian kernel is treated as zero when it is truncated.
+const GAUSSIAN_RADIUS: f64 = 8.0;
+
+/// Radius beyond which the logistic kernel is treated as zero when it is tabulated or truncated.
+const LOGISTIC_RADIUS: f64 = 20.0;
+
+/// Number of bisection steps used to invert a CDF, which narrows the initial bracket down to the precision of `f64`.
+pub(crate) const QUANTILE_BISECTIONS: usize = 64;
+
+/// Number of tabulated points of a kernel's self-convolution.
+const CONVOLUTION_TABLE_SIZE: usize = 2048;
+
+/// Number of Simpson intervals used to integrate each tabulated point of a self-convolution.
+const CONVOLUTION_INTERVALS: usize = 400;
+
+/// Number of kernel variants, i.e. of cached self-convolutions.
+const KERNEL_COUNT: usize = 8;
+
+/// The kernel function used to smooth each sample point.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Kernel {
+    #[serde(alias = "normal")]
+    Gaussian,
+    Epanechnikov,
+    Triangular,
+    #[serde(alias = "tophat")]
+    Uniform,
+    #[serde(alias = "quartic")]
+    Biweight,
+    Triweight,
+    Cosine,
+    Logistic,
+}
+
+impl Kernel {
+    /// Evaluates the kernel at the standardised distance `u`.
+    pub(crate) fn pdf(&self, u: f64) -> f64 {
+        match self {
+            Kernel::Gaussian => FRAC_1_SQRT_2 / PI.sqrt() * (-0.5 * u * u).exp(),
+            Kernel::Logistic => {
+                let e = (-u.abs()).exp();
+                e / ((1.0 + e) * (1.0 + e))
+            }
+            _ if u.abs() > 1.0 => 0.0,
+            Kernel::Epanechnikov => 0.75 * (1.0 - u * u),
+            Kernel::Triangular => 1.0 - u.abs(),
+            Kernel::Uniform => 0.5,
+            Kernel::Biweight => 15.0 / 16.0 * (1.0 - u * u).powi(2),
+            Kernel::Triweight => 35.0 / 32.0 * (1.0 - u * u).powi(3),
+            Kernel::Cosine => PI / 4.0 * (PI / 2.0 * u).cos(),
+        }
+    }
+
+    /// Evaluates the logarithm of the kernel at the standardised distance `u`, which stays finite far in
+    /// the tails of kernels with unbounded support.
+    pub(crate) fn log_pdf(&self, u: f64) -> f64 {
+        match self {
+            Kernel::Gaussian => -0.5 * u * u - 0.5 * (2.0 * PI).ln(),
+            Kernel::Logistic => {
+                let a = u.abs();
+                -a - 2.0 * (-a).exp().ln_1p()
+            }
+            _ => self.pdf(u).ln(),
+        }
+    }
+
+    /// Evaluates the cumulative distribution function of the kernel, i.e. its integral from `-∞` to `u`.
+    pub(crate) fn cdf(&self, u: f64) -> f64 {
+        match self {
+            Kernel::Gaussian => 0.5 * erfc(-u * FRAC_1_SQRT_2),
+            Kernel::Logistic => 1.0 / (1.0 + (-u).exp()),
+            _ if u <= -1.0 => 0.0,
+            _ if u >= 1.0 => 1.0,
+            Kernel::Epanechnikov => 0.5 + 0.75 * (u - u.powi(3) / 3.0),
+            Kernel::Triangular if u < 0.0 => 0.5 * (1.0 + u).powi(2),
+            Kernel::Triangular => 1.0 - 0.5 * (1.0 - u).powi(2),
+            Kernel::Uniform => 0.5 * (1.0 + u),
+            Kernel::Biweight => 0.5 + 15.0 / 16.0 * (u - 2.0 * u.powi(3) / 3.0 + u.powi(5) / 5.0),
+            Kernel::Triweight => {
+                0.5 + 35.0 / 32.0 * (u - u.powi(3) + 3.0 * u.powi(5) / 5.0 - u.powi(7) / 7.0)
+            }
+            Kernel::Cosine => 0.5 * (1.0 + (PI / 2.0 * u).sin()),
+        }
+    }
+
+    /// Evaluates the shadow of the kernel, `-K'(u) / u`, which weighs the sample points in a mean shift step.
+    /// The piecewise linear `Triangular` and piecewise constant `Uniform` kernels have no usable gradient and
+    /// yield zero.
+    pub(crate) fn shadow(&self, u: f64) -> f64 {
+        match self {
+            Kernel::Gaussian => self.pdf(u),
+            Kernel::Logistic if u == 0.0 => 0.5 * self.pdf(0.0),
+            Kernel::Logistic => self.pdf(u) * (0.5 * u).tanh() / u,
+            Kernel::Triangular | Kernel::Uniform => 0.0,
+            _ if u.abs() >= 1.0 => 0.0,
+            Kernel::Epanechnikov => 1.5,
+            Kernel::Biweight => 3.75 * (1.0 - u * u),
+            Kernel::Triweight => 105.0 / 16.0 * (1.0 - u * u).powi(2),
+            Kernel::Cosine if u == 0.0 => PI.powi(3) / 16.0,
+            Kernel::Cosine => PI * PI / 8.0 * (PI / 2.0 * u).sin() / u,
+        }
+    }
+
+    /// Returns the `p`-quantile of the kernel, i.e. the inverse of its CDF, which maps uniform random
+    /// numbers to kernel noise. Kernels without a closed form are inverted by bisection within their radius.
+    pub(crate) fn quantile(&self, p: f64) -> f64 {
+        match self {
+            Kernel::Uniform => 2.0 * p - 1.0,
+            Kernel::Logistic => (p / (1.0 - p)).ln(),
+            _ => {
+                let (mut lo, mut hi) = (-self.radius(), self.radius());
+                for _ in 0..QUANTILE_BISECTIONS {
+                    let mid = 0.5 * (lo + hi);
+                    if self.cdf(mid) < p {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+                0.5 * (lo + hi)
+            }
+        }
+    }
+
+    /// Returns the radius of the kernel's support, or `None` if the support is unbounded.
+    pub(crate) fn support(&self) -> Option<f64> {
+        match self {
+            Kernel::Gaussian | Kernel::Logistic => None,
+            _ => Some(1.0),
+        }
+    }
+
+    /// Returns the radius beyond which the kernel is negligible: the support of compact kernels, and
+    /// a truncation radius for kernels with unbounded support.
+    pub(crate) fn radius(&self) -> f64 {
+        match self {
+            Kernel::Gaussian => GAUSSIAN_RADIUS,
+            Kernel::Logistic => LOGISTIC_RADIUS,
+            _ => 1.0,
+        }
+    }
+}
+
+/// The kernel convolved with itself, `(K * K)(u)`, as required by least-squares cross-validation.
+///
+/// The Gaussian case is evaluated analytically, all other kernels are tabulated once per process and
+/// linearly interpolated.
+pub(crate) struct SelfConvolution {
+    kernel: Kernel,
+    radius: f64,
+    table: Vec<f64>,
+}
+
+impl SelfConvolution {
+    /// Returns the self-convolution of `kernel`, which is tabulated on first use and shared afterwards.
+    pub(crate) fn of(kernel: Kernel) -> &'static Self {
+        static CACHE: [OnceLock<SelfConvolution>; KERNEL_COUNT] =
+            [const { OnceLock::new() }; KERNEL_COUNT];
+        CACHE[kernel as usize].get_or_init(|| SelfConvolution::new(kernel))
+    }
+
+    fn new(kernel: Kernel) -> Self {
+        if kernel == Kernel::Gaussian {
+            return SelfConvolution {
+                kernel,
+                radius: f64::INFINITY,
+                table: Vec::new(),
+            };
+        }
+
+        let r = kernel.radius();
+        let step = 2.0 * r / (CONVOLUTION_TABLE_SIZE - 1) as f64;
+        let dt = 2.0 * r / CONVOLUTION_INTERVALS as f64;
+        let table = (0..CONVOLUTION_TABLE_SIZE)
+            .map(|i| {
+                let u = i as f64 * step;
+                let integral: f64 = (0..=CONVOLUTION_INTERVALS)
+                    .map(|j| {
+                        let t = -r + j as f64 * dt;
+                        let weight = match j {
+                            0 => 1.0,
+                            _ if j == CONVOLUTION_INTERVALS => 1.0,
+                            _ if j % 2 == 1 => 4.0,
+                            _ => 2.0,
+                        };
+                        weight * kernel.pdf(t) * kernel.pdf(u - t)
+                    })
+                    .sum();
+                integral * dt / 3.0
+            })
+            .collect();
+
+        SelfConvolution {
+            kernel,
+            radius: 2.0 * r,
+            table,
+        }
+    }
+
+    /// Evaluates `(K * K)(u)`.
+    pub(crate) fn eval(&self, u: f64) -> f64 {
+        if self.kernel == Kernel::Gaussian {
+            return (-0.25 * u * u).exp() / (4.0 * PI).sqrt();
+        }
+
+        let u = u.abs();
+        if u >= self.radius {
+            return 0.0;
+        }
+        let pos = u / self.radius * (self.table.len() - 1) as f64;
+        let idx = pos as usize;
+        let frac = pos - idx as f64;
+        self.table[idx] * (1.0 - frac) + self.table[idx + 1] * frac
+    }
+}
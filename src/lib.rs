@@ -1,4 +1,14 @@
+mod bandwidth;
+mod binned;
+mod bootstrap;
+mod boundary;
 mod expressions;
+mod kernels;
+mod modes;
+mod multivariate;
+mod periodic;
+mod sample;
+mod tree;
 
 use pyo3_polars::PolarsAllocator;
 
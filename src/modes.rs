@@ -0,0 +1,203 @@
+//! Mode finding on a KDE by mean shift.
+//!
+//! The mean shift step `x ← Σ wᵢ g(uᵢ) xᵢ / Σ wᵢ g(uᵢ)`, where `g(u) = -K'(u) / u` is the shadow of
+//! the kernel, is a gradient ascent on the KDE with an adaptive step size. Started from the sample
+//! points, it converges to the local maxima of the density, which always lie within the sample
+//! range. Each mode is reported with its prominence: its height above the highest valley that
+//! separates it from a higher mode, or from the tails of the density if there is none.
+
+use crate::kernels::Kernel;
+use crate::sample::Sample;
+
+/// Maximum number of mean shift steps from each starting point.
+const MAX_ITERATIONS: usize = 1000;
+
+/// Mean shift stops once a step is shorter than this fraction of the bandwidth.
+const STEP_TOLERANCE: f64 = 1e-9;
+
+/// Converged points closer than this fraction of the bandwidth are merged into one mode. It also
+/// serves as the offset at which a converged point is checked to be a local maximum.
+const MERGE_TOLERANCE: f64 = 1e-4;
+
+/// Starting points closer than this fraction of the bandwidth to the previous one are skipped,
+/// since they converge to the same mode.
+const START_SPACING: f64 = 0.25;
+
+/// Number of golden-section steps used to locate the valley between two neighbouring modes.
+const VALLEY_ITERATIONS: usize = 100;
+
+/// A local maximum of the density.
+pub(crate) struct Mode {
+    pub(crate) x: f64,
+    pub(crate) density: f64,
+    pub(crate) prominence: f64,
+}
+
+/// A sample sorted by its points, so that the points within the kernel radius are found by binary
+/// search.
+struct SortedKde {
+    points: Vec<f64>,
+    weights: Vec<f64>,
+    kernel: Kernel,
+    bandwidth: f64,
+    norm: f64,
+}
+
+impl SortedKde {
+    fn new(sample: &Sample, kernel: Kernel, bandwidth: f64) -> Self {
+        let mut pairs = sample.iter().collect::<Vec<_>>();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (points, weights) = pairs.into_iter().unzip();
+        SortedKde {
+            points,
+            weights,
+            kernel,
+            bandwidth,
+            norm: 1.0 / (sample.total_weight() * bandwidth),
+        }
+    }
+
+    /// Iterates over the standardised distances and weights of the points within the kernel radius of `x`.
+    fn window(&self, x: f64) -> impl Iterator<Item = (f64, f64, f64)> + '_ {
+        let radius = self.kernel.radius() * self.bandwidth;
+        let lo = self.points.partition_point(|&p| p < x - radius);
+        let hi = self.points.partition_point(|&p| p <= x + radius);
+        self.points[lo..hi]
+            .iter()
+            .zip(&self.weights[lo..hi])
+            .map(move |(&p, &w)| (p, (x - p) / self.bandwidth, w))
+    }
+
+    fn density(&self, x: f64) -> f64 {
+        self.window(x)
+            .map(|(_, u, w)| w * self.kernel.pdf(u))
+            .sum::<f64>()
+            * self.norm
+    }
+
+    /// Follows the mean shift from `x` until it converges, or until no sample point is within reach.
+    fn climb(&self, mut x: f64) -> f64 {
+        for _ in 0..MAX_ITERATIONS {
+            let (num, den) = self.window(x).fold((0.0, 0.0), |(num, den), (p, u, w)| {
+                let g = w * self.kernel.shadow(u);
+                (num + g * p, den + g)
+            });
+            if den <= 0.0 {
+                break;
+            }
+            let next = num / den;
+            let step = (next - x).abs();
+            x = next;
+            if step < STEP_TOLERANCE * self.bandwidth {
+                break;
+            }
+        }
+        x
+    }
+
+    /// Returns the lowest density between the neighbouring modes `a < b` by golden-section search.
+    fn valley(&self, mut a: f64, mut b: f64) -> f64 {
+        let ratio = 0.5 * (5f64.sqrt() - 1.0);
+        let mut c = b - ratio * (b - a);
+        let mut d = a + ratio * (b - a);
+        let (mut fc, mut fd) = (self.density(c), self.density(d));
+        for _ in 0..VALLEY_ITERATIONS {
+            if fc < fd {
+                b = d;
+                (d, fd) = (c, fc);
+                c = b - ratio * (b - a);
+                fc = self.density(c);
+            } else {
+                a = c;
+                (c, fc) = (d, fd);
+                d = a + ratio * (b - a);
+                fd = self.density(d);
+            }
+        }
+        fc.min(fd)
+    }
+}
+
+/// Finds the local maxima of the KDE of a (weighted) sample, in increasing order, keeping those whose
+/// prominence is at least `min_prominence` times the height of the highest mode.
+///
+/// The kernel must have a usable gradient, i.e. neither `Uniform` nor `Triangular`.
+pub(crate) fn find_modes(
+    sample: &Sample,
+    kernel: Kernel,
+    bandwidth: f64,
+    min_prominence: f64,
+) -> Vec<Mode> {
+    let kde = SortedKde::new(sample, kernel, bandwidth);
+    let tolerance = MERGE_TOLERANCE * bandwidth;
+
+    let mut starts: Vec<f64> = Vec::new();
+    for &p in &kde.points {
+        if !starts
+            .last()
+            .is_some_and(|&last| p - last < START_SPACING * bandwidth)
+        {
+            starts.push(p);
+        }
+    }
+    let mut peaks = starts.into_iter().map(|x| kde.climb(x)).collect::<Vec<_>>();
+    peaks.sort_by(f64::total_cmp);
+
+    // merge the points that converged to the same mode, and drop the stationary points that are no maxima
+    let mut modes: Vec<(f64, f64)> = Vec::new();
+    let mut cluster: Vec<f64> = Vec::new();
+    for x in peaks.into_iter().chain([f64::INFINITY]) {
+        if cluster.last().is_some_and(|&last| x - last > tolerance) {
+            let center = cluster.iter().sum::<f64>() / cluster.len() as f64;
+            let density = kde.density(center);
+            if density >= kde.density(center - tolerance)
+                && density >= kde.density(center + tolerance)
+            {
+                modes.push((center, density));
+            }
+            cluster.clear();
+        }
+        cluster.push(x);
+    }
+
+    let valleys = modes
+        .windows(2)
+        .map(|pair| kde.valley(pair[0].0, pair[1].0))
+        .collect::<Vec<_>>();
+    let highest = modes.iter().map(|m| m.1).fold(0.0, f64::max);
+
+    (0..modes.len())
+        .filter_map(|i| {
+            let density = modes[i].1;
+            // the highest valley on each side before a higher mode, or zero in the tails
+            let mut left = f64::INFINITY;
+            let mut j = i;
+            while j > 0 && modes[j - 1].1 <= density {
+                left = left.min(valleys[j - 1]);
+                j -= 1;
+            }
+            let left = if j == 0 {
+                0.0
+            } else {
+                left.min(valleys[j - 1])
+            };
+            let mut right = f64::INFINITY;
+            let mut j = i;
+            while j + 1 < modes.len() && modes[j + 1].1 <= density {
+                right = right.min(valleys[j]);
+                j += 1;
+            }
+            let right = if j + 1 == modes.len() {
+                0.0
+            } else {
+                right.min(valleys[j])
+            };
+            let prominence = density - left.max(right);
+            (prominence >= min_prominence * highest).then_some(Mode {
+                x: modes[i].0,
+                density,
+                prominence,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,212 @@
+//! Multivariate kernel density estimation with a Gaussian kernel.
+//!
+//! The kernel is smoothed by a bandwidth matrix `H`, which is derived from the sample covariance
+//! by Scott's or Silverman's rule, or set to `h² I` for a fixed bandwidth `h`.
+
+use serde::Deserialize;
+use std::f64::consts::PI;
+
+/// A rule used to derive the bandwidth matrix from the sample covariance.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MultivariateRule {
+    Scott,
+    Silverman,
+}
+
+/// The bandwidth of the multivariate estimator, either derived from the data by a rule or a fixed
+/// positive scalar that is applied to every dimension.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum MultivariateBandwidth {
+    Rule(MultivariateRule),
+    Fixed(f64),
+}
+
+/// The shape of the bandwidth matrix derived by a rule.
+///
+/// - `ScaledIdentity`: the average variance of all dimensions times the identity, i.e. the same bandwidth in every dimension.
+/// - `Diagonal`: the variances of each dimension, i.e. a product kernel.
+/// - `Full`: the full covariance matrix, which also captures correlations between dimensions.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BandwidthMatrix {
+    #[serde(alias = "identity")]
+    ScaledIdentity,
+    Diagonal,
+    Full,
+}
+
+impl MultivariateRule {
+    /// Returns the factor by which the sample standard deviations are scaled for `n` points in `dim`
+    /// dimensions: `n^(-1/(d+4))` for Scott's rule and `(n (d+2) / 4)^(-1/(d+4))` for Silverman's rule.
+    fn factor(&self, n: f64, dim: usize) -> f64 {
+        let d = dim as f64;
+        match self {
+            MultivariateRule::Scott => n.powf(-1.0 / (d + 4.0)),
+            MultivariateRule::Silverman => (n * (d + 2.0) / 4.0).powf(-1.0 / (d + 4.0)),
+        }
+    }
+}
+
+/// The sample points of a multivariate sample, stored row-major.
+pub(crate) struct MultivariateSample {
+    dim: usize,
+    points: Vec<f64>,
+}
+
+impl MultivariateSample {
+    /// Creates a sample of `points.len() / dim` points from a row-major vector of coordinates.
+    pub(crate) fn new(dim: usize, points: Vec<f64>) -> Self {
+        MultivariateSample { dim, points }
+    }
+
+    /// Returns the number of sample points.
+    pub(crate) fn len(&self) -> usize {
+        self.points.len() / self.dim
+    }
+
+    /// Iterates over the sample points.
+    fn rows(&self) -> impl Iterator<Item = &[f64]> {
+        self.points.chunks_exact(self.dim)
+    }
+
+    /// Returns the sample covariance matrix (with Bessel's correction), stored row-major.
+    fn covariance(&self) -> Vec<f64> {
+        let n = self.len() as f64;
+        let mut mean = vec![0.0; self.dim];
+        for row in self.rows() {
+            for (m, x) in mean.iter_mut().zip(row) {
+                *m += x / n;
+            }
+        }
+
+        let mut cov = vec![0.0; self.dim * self.dim];
+        for row in self.rows() {
+            let deviations = row
+                .iter()
+                .zip(&mean)
+                .map(|(x, m)| x - m)
+                .collect::<Vec<_>>();
+            for (i, di) in deviations.iter().enumerate() {
+                for (j, dj) in deviations[..=i].iter().enumerate() {
+                    cov[i * self.dim + j] += di * dj / (n - 1.0);
+                }
+            }
+        }
+        for i in 0..self.dim {
+            for j in 0..i {
+                cov[j * self.dim + i] = cov[i * self.dim + j];
+            }
+        }
+        cov
+    }
+}
+
+/// Returns the matrix `scale * I` of dimension `dim`, stored row-major.
+fn scaled_identity(scale: f64, dim: usize) -> Vec<f64> {
+    let mut matrix = vec![0.0; dim * dim];
+    for i in 0..dim {
+        matrix[i * dim + i] = scale;
+    }
+    matrix
+}
+
+/// Returns the lower triangular Cholesky factor `L` of a symmetric matrix `A = L Lᵀ`, or `None`
+/// if `A` is not positive definite.
+fn cholesky(a: &[f64], dim: usize) -> Option<Vec<f64>> {
+    let mut l = vec![0.0; dim * dim];
+    for i in 0..dim {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| l[i * dim + k] * l[j * dim + k]).sum();
+            if i == j {
+                let diag = a[i * dim + i] - sum;
+                if diag <= 0.0 || !diag.is_finite() {
+                    return None;
+                }
+                l[i * dim + i] = diag.sqrt();
+            } else {
+                l[i * dim + j] = (a[i * dim + j] - sum) / l[j * dim + j];
+            }
+        }
+    }
+    Some(l)
+}
+
+/// A Gaussian kernel density estimator with bandwidth matrix `H = L Lᵀ`.
+pub(crate) struct MultivariateKde<'a> {
+    sample: &'a MultivariateSample,
+    cholesky: Vec<f64>,
+    norm: f64,
+}
+
+impl<'a> MultivariateKde<'a> {
+    /// Derives the bandwidth matrix for the sample.
+    ///
+    /// Returns `None` if the bandwidth matrix is singular, e.g. because there are too few sample points
+    /// or because they are collinear.
+    pub(crate) fn new(
+        sample: &'a MultivariateSample,
+        bandwidth: MultivariateBandwidth,
+        matrix: BandwidthMatrix,
+        bw_adjust: f64,
+    ) -> Option<Self> {
+        let dim = sample.dim;
+        if sample.len() <= 1 {
+            return None;
+        }
+
+        let (mut h, scale) = match bandwidth {
+            MultivariateBandwidth::Fixed(h) => (scaled_identity(1.0, dim), h),
+            MultivariateBandwidth::Rule(rule) => {
+                let mut cov = sample.covariance();
+                match matrix {
+                    BandwidthMatrix::ScaledIdentity => {
+                        let trace: f64 = (0..dim).map(|i| cov[i * dim + i]).sum();
+                        cov = scaled_identity(trace / dim as f64, dim);
+                    }
+                    BandwidthMatrix::Diagonal => {
+                        for i in 0..dim {
+                            for j in (0..dim).filter(|&j| j != i) {
+                                cov[i * dim + j] = 0.0;
+                            }
+                        }
+                    }
+                    BandwidthMatrix::Full => {}
+                }
+                (cov, rule.factor(sample.len() as f64, dim))
+            }
+        };
+        let scale = scale * bw_adjust;
+        h.iter_mut().for_each(|x| *x *= scale * scale);
+
+        let cholesky = cholesky(&h, dim)?;
+        let det_sqrt: f64 = (0..dim).map(|i| cholesky[i * dim + i]).product();
+        let norm = 1.0 / (sample.len() as f64 * (2.0 * PI).powf(dim as f64 / 2.0) * det_sqrt);
+
+        Some(MultivariateKde {
+            sample,
+            cholesky,
+            norm,
+        })
+    }
+
+    /// Evaluates the density at the point `x`, which must have the dimension of the sample.
+    pub(crate) fn density(&self, x: &[f64]) -> f64 {
+        let dim = self.sample.dim;
+        let mut z = vec![0.0; dim];
+        let sum: f64 = self
+            .sample
+            .rows()
+            .map(|row| {
+                // solve L z = x - x_i by forward substitution, so that |z|² = (x - x_i)ᵀ H⁻¹ (x - x_i)
+                for (i, (xi, ri)) in x.iter().zip(row).enumerate() {
+                    let s: f64 = (0..i).map(|k| self.cholesky[i * dim + k] * z[k]).sum();
+                    z[i] = (xi - ri - s) / self.cholesky[i * dim + i];
+                }
+                (-0.5 * z.iter().map(|z| z * z).sum::<f64>()).exp()
+            })
+            .sum();
+        sum * self.norm
+    }
+}
@@ -0,0 +1,52 @@
+//! Kernel density estimation on a periodic domain, such as the hour of the day or an angle.
+//!
+//! The kernel contributions are wrapped around the period, so that the density is periodic and
+//! continuous across the wrap point. This equals the KDE of the sample points together with their
+//! translations by multiples of the period, evaluated within one period starting at zero. With a
+//! Gaussian kernel, it is the wrapped normal distribution, which closely resembles a von Mises kernel.
+
+use crate::sample::Sample;
+
+/// Returns the sample points wrapped into `[0, period)`, together with their translations by
+/// multiples of the period that are within `reach` of that interval.
+pub(crate) fn wrap_sample(sample: &Sample, period: f64, reach: f64) -> Sample {
+    let (points, weights) = sample
+        .iter()
+        .flat_map(|(x, w)| {
+            let x = x.rem_euclid(period);
+            let first = ((-reach - x) / period).ceil() as i64;
+            let last = ((period + reach - x) / period).floor() as i64;
+            (first..=last).map(move |k| (x + k as f64 * period, w))
+        })
+        .unzip();
+    Sample::new(points, weights)
+}
+
+/// Returns the sample points wrapped into one period and cut open at the largest gap between them,
+/// so that points on both sides of the wrap point stay close, e.g. for the selection of the bandwidth.
+pub(crate) fn unwrap_sample(sample: &Sample, period: f64) -> Sample {
+    let mut pairs = sample
+        .iter()
+        .map(|(x, w)| (x.rem_euclid(period), w))
+        .collect::<Vec<_>>();
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // the gap after the i-th point, which wraps around after the last point
+    let n = pairs.len();
+    let cut = (0..n)
+        .max_by(|&i, &j| {
+            let gap = |i: usize| match pairs.get(i + 1) {
+                Some(next) => next.0 - pairs[i].0,
+                None => pairs[0].0 + period - pairs[i].0,
+            };
+            gap(i).total_cmp(&gap(j))
+        })
+        .unwrap_or(n);
+    if cut + 1 < n {
+        for pair in &mut pairs[..=cut] {
+            pair.0 += period;
+        }
+    }
+    let (points, weights) = pairs.into_iter().unzip();
+    Sample::new(points, weights)
+}
@@ -0,0 +1,155 @@
+//! The weighted sample points of a single group or list row.
+
+/// The sample points of a single group or list row, together with their non-negative weights.
+///
+/// Unweighted samples carry unit weights, so that every estimator only has to handle the weighted case. The weights
+/// are reliability weights, as in `scipy.stats.gaussian_kde`, rather than frequencies: integer weights do not give
+/// the same sample size and spread as repeating the points.
+pub(crate) struct Sample {
+    pub(crate) points: Vec<f64>,
+    pub(crate) weights: Vec<f64>,
+    total_weight: f64,
+}
+
+impl Sample {
+    /// Creates a weighted sample. Sample points with zero weight are dropped.
+    pub(crate) fn new(points: Vec<f64>, weights: Vec<f64>) -> Self {
+        let (points, weights): (Vec<_>, Vec<_>) = points
+            .into_iter()
+            .zip(weights)
+            .filter(|(_, w)| *w > 0.0)
+            .unzip();
+        let total_weight = weights.iter().sum();
+        Sample {
+            points,
+            weights,
+            total_weight,
+        }
+    }
+
+    /// Creates a sample in which every point has unit weight.
+    pub(crate) fn unweighted(points: Vec<f64>) -> Self {
+        let weights = vec![1.0; points.len()];
+        let total_weight = points.len() as f64;
+        Sample {
+            points,
+            weights,
+            total_weight,
+        }
+    }
+
+    /// Returns the number of sample points.
+    pub(crate) fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns whether the sample has no points.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the sum of the weights.
+    pub(crate) fn total_weight(&self) -> f64 {
+        self.total_weight
+    }
+
+    /// Returns the sum of the squared weights.
+    pub(crate) fn squared_weight(&self) -> f64 {
+        self.weights.iter().map(|w| w * w).sum()
+    }
+
+    /// Returns Kish's effective sample size `(Σw)² / Σw²`, which equals the number of points for unit weights.
+    pub(crate) fn effective_size(&self) -> f64 {
+        self.total_weight * self.total_weight / self.squared_weight()
+    }
+
+    /// Iterates over the pairs of sample points and weights.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.points
+            .iter()
+            .copied()
+            .zip(self.weights.iter().copied())
+    }
+
+    /// Returns the smallest and the largest sample point.
+    pub(crate) fn range(&self) -> (f64, f64) {
+        self.points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| {
+                (lo.min(x), hi.max(x))
+            })
+    }
+
+    /// Returns the weighted mean of the sample points.
+    pub(crate) fn mean(&self) -> f64 {
+        self.iter().map(|(x, w)| w * x).sum::<f64>() / self.total_weight
+    }
+
+    /// Returns the weighted standard deviation, with the reliability weights analogue of Bessel's correction.
+    pub(crate) fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        let sum_sq = self
+            .iter()
+            .map(|(x, w)| w * (x - mean).powi(2))
+            .sum::<f64>();
+        let var = sum_sq / (self.total_weight - self.squared_weight() / self.total_weight);
+        var.sqrt()
+    }
+
+    /// Returns the weighted `q`-quantile, which reduces to linear interpolation between order statistics
+    /// for unit weights.
+    ///
+    /// The `i`-th smallest point is placed at `(C_i - w_i) / (W - w_last)`, where `C_i` is the cumulative weight.
+    pub(crate) fn quantile(&self, q: f64) -> f64 {
+        let mut sorted = self.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let (last, w_last) = sorted[sorted.len() - 1];
+        let denominator = self.total_weight - w_last;
+        if denominator <= 0.0 {
+            return last;
+        }
+
+        let mut cumulative = 0.0;
+        let mut previous: Option<(f64, f64)> = None;
+        for &(x, w) in &sorted {
+            let position = cumulative / denominator;
+            cumulative += w;
+            if position >= q {
+                return match previous {
+                    Some((p_prev, x_prev)) if position > p_prev => {
+                        x_prev + (x - x_prev) * (q - p_prev) / (position - p_prev)
+                    }
+                    _ => x,
+                };
+            }
+            previous = Some((position, x));
+        }
+        last
+    }
+
+    /// Returns the weighted interquartile range.
+    pub(crate) fn iqr(&self) -> f64 {
+        self.quantile(0.75) - self.quantile(0.25)
+    }
+
+    /// Returns the weighted `q`-quantile as the smallest point whose cumulative weight reaches the rank
+    /// `round(q (W + 1))`, without interpolation.
+    ///
+    /// For unit weights this is the order statistic used by the `kernel-density-estimation` crate, and
+    /// integer weights give the same result as repeating each point.
+    pub(crate) fn rank_quantile(&self, q: f64) -> f64 {
+        let mut sorted = self.iter().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let rank = (q * (self.total_weight + 1.0)).round();
+        let mut cumulative = 0.0;
+        for &(x, w) in &sorted {
+            cumulative += w;
+            if cumulative >= rank {
+                return x;
+            }
+        }
+        sorted[sorted.len() - 1].0
+    }
+}
@@ -0,0 +1,141 @@
+//! Approximate kernel density estimation on a sorted sample.
+//!
+//! In one dimension, a search tree over the sample reduces to the sorted sample itself: the points
+//! within a window around an evaluation point are found by binary search. The window is widened
+//! until the contributions of all points outside of it are provably within the tolerance, which
+//! prunes distant points for kernels with compact or rapidly decaying support. For the CDF, the
+//! points left of the window contribute their full weight.
+
+use crate::kernels::Kernel;
+use crate::sample::Sample;
+
+/// A sample sorted by its points, with the cumulative weights for the weight outside of a window.
+struct SortedSample {
+    points: Vec<f64>,
+    weights: Vec<f64>,
+    cumulative: Vec<f64>,
+}
+
+impl SortedSample {
+    fn new(sample: &Sample) -> Self {
+        let mut pairs = sample.iter().collect::<Vec<_>>();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (points, weights): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+
+        let mut cumulative = Vec::with_capacity(weights.len() + 1);
+        cumulative.push(0.0);
+        for w in &weights {
+            cumulative.push(cumulative[cumulative.len() - 1] + w);
+        }
+        SortedSample {
+            points,
+            weights,
+            cumulative,
+        }
+    }
+}
+
+/// Computes the KDE of a (weighted) sample at the evaluation points, neglecting the contributions of
+/// distant sample points as long as the error at each evaluation point stays within
+/// `atol + rtol * density`.
+///
+/// The window around an evaluation point starts at one bandwidth and is doubled until the kernel at
+/// the edge of the window, times the weight outside of it, is below the tolerance. Since all kernels
+/// decrease with the distance, this bounds the neglected contributions. With zero tolerances, the
+/// result is exact and only compact kernels are pruned.
+pub(crate) fn tree_kde(
+    sample: &Sample,
+    eval_points: &[f64],
+    kernel: Kernel,
+    bandwidth: f64,
+    rtol: f64,
+    atol: f64,
+) -> Vec<f64> {
+    let sorted = SortedSample::new(sample);
+    let n = sorted.points.len();
+    let total = sample.total_weight();
+    let norm = 1.0 / (total * bandwidth);
+    let support = kernel.support().unwrap_or(f64::INFINITY);
+
+    eval_points
+        .iter()
+        .map(|&x| {
+            let start = sorted.points.partition_point(|&p| p < x);
+            let (mut lo, mut hi) = (start, start);
+            let mut radius: f64 = 1.0;
+            let mut sum = 0.0;
+            loop {
+                let window = radius * bandwidth;
+                let new_lo = sorted.points[..lo].partition_point(|&p| p < x - window);
+                let new_hi = hi + sorted.points[hi..].partition_point(|&p| p <= x + window);
+                sum += (new_lo..lo)
+                    .chain(hi..new_hi)
+                    .map(|i| sorted.weights[i] * kernel.pdf((x - sorted.points[i]) / bandwidth))
+                    .sum::<f64>();
+                (lo, hi) = (new_lo, new_hi);
+
+                if (lo == 0 && hi == n) || radius >= support {
+                    break;
+                }
+                let outside = total - (sorted.cumulative[hi] - sorted.cumulative[lo]);
+                let bound = kernel.pdf(radius) * outside * norm;
+                if bound <= atol + rtol * sum * norm {
+                    break;
+                }
+                radius *= 2.0;
+            }
+            sum * norm
+        })
+        .collect()
+}
+
+/// Computes the CDF of the KDE of a (weighted) sample at the evaluation points, counting the sample
+/// points left of a window around each evaluation point in full and neglecting those right of it.
+///
+/// Each point outside of a window of `radius` bandwidths is off by at most `K_cdf(-radius)`, so the
+/// window is doubled until this bound, times the weight outside of it, is within
+/// `atol + rtol * cdf`. The result is exact for compact kernels.
+pub(crate) fn tree_cdf(
+    sample: &Sample,
+    eval_points: &[f64],
+    kernel: Kernel,
+    bandwidth: f64,
+    rtol: f64,
+    atol: f64,
+) -> Vec<f64> {
+    let sorted = SortedSample::new(sample);
+    let n = sorted.points.len();
+    let total = sample.total_weight();
+    let support = kernel.support().unwrap_or(f64::INFINITY);
+
+    eval_points
+        .iter()
+        .map(|&x| {
+            let start = sorted.points.partition_point(|&p| p < x);
+            let (mut lo, mut hi) = (start, start);
+            let mut radius: f64 = 1.0;
+            let mut sum = 0.0;
+            loop {
+                let window = radius * bandwidth;
+                let new_lo = sorted.points[..lo].partition_point(|&p| p < x - window);
+                let new_hi = hi + sorted.points[hi..].partition_point(|&p| p <= x + window);
+                sum += (new_lo..lo)
+                    .chain(hi..new_hi)
+                    .map(|i| sorted.weights[i] * kernel.cdf((x - sorted.points[i]) / bandwidth))
+                    .sum::<f64>();
+                (lo, hi) = (new_lo, new_hi);
+
+                if (lo == 0 && hi == n) || radius >= support {
+                    break;
+                }
+                let outside = total - (sorted.cumulative[hi] - sorted.cumulative[lo]);
+                let bound = kernel.cdf(-radius) * outside / total;
+                if bound <= atol + rtol * (sum + sorted.cumulative[lo]) / total {
+                    break;
+                }
+                radius *= 2.0;
+            }
+            (sum + sorted.cumulative[lo]) / total
+        })
+        .collect()
+}
@@ -1,3 +1,8 @@
+import math
+import random
+import statistics
+from datetime import date, datetime, time, timedelta
+
 import pytest
 import polars as pl
 import polars_kde as pkde
@@ -36,10 +41,10 @@ def test_kde_static_evals(sample_df, eval_points):
     )
 
     assert df_kde.shape == (2, 3)
-    assert df_kde.select("kde").dtypes[0] == pl.List(pl.Float32)
+    assert df_kde.select("kde").dtypes[0] == pl.Array(pl.Float32, 5)
 
     assert_series_equal(
-        df_kde["kde"].list.len(),
+        df_kde["kde"].arr.len(),
         pl.Series([5, 5]),
         check_names=False,
         check_dtypes=False,
@@ -90,10 +95,10 @@ def test_kde_agg(
     )
 
     assert df_kde.shape == (2, 2)
-    assert df_kde.select("kde").dtypes[0] == pl.List(pl.Float32)
+    assert df_kde.select("kde").dtypes[0] == pl.Array(pl.Float32, 5)
 
     assert_series_equal(
-        df_kde["kde"].list.len(),
+        df_kde["kde"].arr.len(),
         pl.Series([5, 5]),
         check_names=False,
         check_dtypes=False,
@@ -122,11 +127,1481 @@ def test_one_element():
     )
 
     assert df_kde.shape == (1, 2)
-    assert df_kde.select("kde").dtypes[0] == pl.List(pl.Float32)
+    assert df_kde.select("kde").dtypes[0] == pl.Array(pl.Float32, 1)
 
     assert_series_equal(
-        df_kde["kde"].list.len(),
+        df_kde["kde"].arr.len(),
         pl.Series([1]),
         check_names=False,
         check_dtypes=False,
     )
+
+
+@pytest.mark.parametrize(
+    "kernel",
+    [
+        "gaussian",
+        "epanechnikov",
+        "triangular",
+        "uniform",
+        "tophat",
+        "biweight",
+        "triweight",
+        "cosine",
+        "logistic",
+    ],
+)
+def test_kernels(sample_df, eval_points, kernel):
+    df_kde = sample_df.group_by("id").agg(
+        kde=pkde.kde(
+            pl.col("a"),
+            eval_points=eval_points,
+            kernel=kernel,
+        )
+    )
+
+    assert df_kde.shape == (2, 2)
+    assert df_kde.select("kde").dtypes[0] == pl.Array(pl.Float32, 5)
+    assert (df_kde["kde"].explode() >= 0).all()
+
+
+def test_compact_kernel_support(sample_df):
+    df_kde = sample_df.group_by("id").agg(
+        kde=pkde.kde(
+            pl.col("a"),
+            eval_points=[-100.0, 100.0],
+            kernel="epanechnikov",
+        )
+    )
+
+    assert (df_kde["kde"].explode() == 0).all()
+
+
+KERNEL_PDFS = {
+    "gaussian": lambda u: math.exp(-0.5 * u * u) / math.sqrt(2 * math.pi),
+    "epanechnikov": lambda u: 0.75 * (1 - u * u) if abs(u) <= 1 else 0.0,
+    "triangular": lambda u: 1 - abs(u) if abs(u) <= 1 else 0.0,
+    "uniform": lambda u: 0.5 if abs(u) <= 1 else 0.0,
+    "biweight": lambda u: 15 / 16 * (1 - u * u) ** 2 if abs(u) <= 1 else 0.0,
+    "triweight": lambda u: 35 / 32 * (1 - u * u) ** 3 if abs(u) <= 1 else 0.0,
+    "cosine": lambda u: math.pi / 4 * math.cos(math.pi / 2 * u) if abs(u) <= 1 else 0.0,
+    "logistic": lambda u: math.exp(-u) / (1 + math.exp(-u)) ** 2,
+}
+
+
+@pytest.mark.parametrize("kernel", list(KERNEL_PDFS))
+def test_kernel_values(kernel):
+    samples = [0.0, 1.0]
+    h = 2.0
+    x = [-1.5, 0.25, 0.5, 1.5, 3.5]
+    expected = [
+        sum(KERNEL_PDFS[kernel]((xi - s) / h) for s in samples) / (len(samples) * h)
+        for xi in x
+    ]
+
+    df_kde = pl.DataFrame({"a": samples}).select(
+        kde=pkde.kde(pl.col("a"), eval_points=x, kernel=kernel, bandwidth=h)
+    )
+
+    assert df_kde["kde"][0].to_list() == pytest.approx(expected, rel=1e-9, abs=1e-12)
+
+
+@pytest.mark.parametrize("kernel", list(KERNEL_PDFS))
+def test_kernel_integrates_to_one(kernel):
+    step = 0.002
+    x = [-30.0 + i * step for i in range(30001)]
+
+    density = (
+        pl.DataFrame({"a": [0.0]})
+        .select(kde=pkde.kde(pl.col("a"), eval_points=x, kernel=kernel, bandwidth=1.0))[
+            "kde"
+        ][0]
+        .to_list()
+    )
+    integral = step * (sum(density) - 0.5 * (density[0] + density[-1]))
+
+    assert integral == pytest.approx(1.0, abs=2e-3)
+
+
+def test_silverman_matches_baseline():
+    samples = [1.0, 1.5, 2.0, 2.5, 3.0]
+    # quartiles are the order statistics of rank round(q (n + 1)), i.e. 1.5 and 3.0
+    h = 0.9 * min(statistics.stdev(samples), (3.0 - 1.5) / 1.349) * 5**-0.2
+    # the bandwidth of the `kernel-density-estimation` crate for this sample
+    assert h == pytest.approx(0.51568, abs=1e-5)
+
+    x = [0.0, 1.0, 2.2, 4.0]
+    expected = [
+        sum(KERNEL_PDFS["gaussian"]((xi - s) / h) for s in samples) / (len(samples) * h)
+        for xi in x
+    ]
+
+    df_kde = pl.DataFrame({"a": samples}).select(
+        kde=pkde.kde(pl.col("a"), eval_points=x)
+    )
+
+    assert df_kde["kde"][0].to_list() == pytest.approx(expected, rel=1e-9)
+
+
+def test_fixed_bandwidth(sample_df):
+    samples = [1.0, 2.0]
+    x = 1.5
+    h = 0.5
+    expected = sum(
+        math.exp(-0.5 * ((x - xi) / h) ** 2) / math.sqrt(2 * math.pi) for xi in samples
+    ) / (len(samples) * h)
+
+    df_kde = sample_df.filter(pl.col("id") == 0).select(
+        kde=pkde.kde(pl.col("a"), eval_points=[x], bandwidth=h)
+    )
+
+    assert df_kde["kde"].explode()[0] == pytest.approx(expected, rel=1e-5)
+
+
+def test_scott_matches_scipy():
+    samples = [1.0, 1.5, 2.0, 2.5, 3.0]
+    # `scipy.stats.gaussian_kde(samples).factor * statistics.stdev(samples)`
+    h = 0.5729886
+    x = [0.0, 1.0, 2.2, 4.0]
+    expected = [
+        sum(KERNEL_PDFS["gaussian"]((xi - s) / h) for s in samples) / (len(samples) * h)
+        for xi in x
+    ]
+
+    df_kde = pl.DataFrame({"a": samples}).select(
+        kde=pkde.kde(pl.col("a"), eval_points=x, bandwidth="scott")
+    )
+
+    assert df_kde["kde"][0].to_list() == pytest.approx(expected, rel=1e-5)
+
+
+def test_weighted_scott_matches_scipy():
+    samples = [1.0, 2.0, 4.0]
+    weights = [1.0, 2.0, 3.0]
+    total = sum(weights)
+    mean = sum(w * s for s, w in zip(samples, weights)) / total
+    squared = sum(w * w for w in weights)
+    # scipy uses the Kish effective sample size and the unbiased weighted covariance
+    variance = sum(w * (s - mean) ** 2 for s, w in zip(samples, weights)) / (
+        total - squared / total
+    )
+    h = math.sqrt(variance) * (total * total / squared) ** -0.2
+    x = [1.5, 3.0]
+    expected = [
+        sum(w * KERNEL_PDFS["gaussian"]((xi - s) / h) for s, w in zip(samples, weights))
+        / (total * h)
+        for xi in x
+    ]
+
+    df_kde = pl.DataFrame({"a": samples, "w": weights}).select(
+        kde=pkde.kde(pl.col("a"), eval_points=x, weights="w", bandwidth="scott")
+    )
+
+    assert df_kde["kde"][0].to_list() == pytest.approx(expected, rel=1e-9)
+
+
+@pytest.mark.parametrize("bandwidth", ["silverman", "scott", 0.5])
+def test_bw_adjust(sample_df, eval_points, bandwidth):
+    df = sample_df.group_by("id").agg(pl.col("a"))
+
+    df_kde = df.with_columns(
+        adjusted=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, bandwidth=bandwidth, bw_adjust=2.0
+        ),
+        default=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, bandwidth=bandwidth
+        ),
+    )
+
+    # a wider bandwidth flattens the density at the centre of each group
+    assert (df_kde["adjusted"].arr.max() < df_kde["default"].arr.max()).all()
+
+
+@pytest.mark.parametrize(
+    ("bandwidth", "bw_adjust"),
+    [(0.0, 1.0), (-1.0, 1.0), ("silverman", 0.0)],
+)
+def test_invalid_bandwidth(sample_df, eval_points, bandwidth, bw_adjust):
+    with pytest.raises(pl.exceptions.ComputeError):
+        sample_df.group_by("id").agg(
+            kde=pkde.kde(
+                pl.col("a"),
+                eval_points=eval_points,
+                bandwidth=bandwidth,
+                bw_adjust=bw_adjust,
+            )
+        )
+
+
+@pytest.fixture
+def bimodal_df() -> pl.DataFrame:
+    rng = random.Random(0)
+    values = [rng.gauss(-3.0, 0.5) for _ in range(500)] + [
+        rng.gauss(3.0, 0.5) for _ in range(500)
+    ]
+    return pl.DataFrame({"a": values}, schema={"a": pl.Float32})
+
+
+@pytest.mark.parametrize("bandwidth", ["sj", "isj", "sheather_jones"])
+def test_plugin_bandwidth_resolves_modes(bimodal_df, bandwidth):
+    df_kde = bimodal_df.select(
+        silverman=pkde.kde(pl.col("a"), eval_points=[-3.0, 0.0, 3.0]),
+        plugin=pkde.kde(
+            pl.col("a"), eval_points=[-3.0, 0.0, 3.0], bandwidth=bandwidth
+        ),
+    )
+    silverman, plugin = df_kde["silverman"].explode(), df_kde["plugin"].explode()
+
+    # the plug-in selectors use a narrower bandwidth and resolve the valley between the modes
+    assert plugin[1] < silverman[1]
+    assert plugin[0] > silverman[0]
+
+
+@pytest.mark.parametrize("bandwidth", ["sj", "isj"])
+def test_plugin_bandwidth_sparse_groups(sample_df, eval_points, bandwidth):
+    df_kde = sample_df.group_by("id").agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, bandwidth=bandwidth)
+    )
+
+    # groups too small for the plug-in estimates still get a finite bandwidth
+    assert df_kde["kde"].explode().is_finite().all()
+
+
+@pytest.mark.parametrize("bandwidth", ["likelihood_cv", "least_squares_cv"])
+def test_cv_bandwidth(bimodal_df, bandwidth):
+    df = bimodal_df.select(pl.col("a").implode())
+
+    df_kde = df.select(
+        silverman=pkde.kde_static_evals(pl.col("a"), eval_points=[-3.0, 0.0, 3.0]),
+        cv=pkde.kde_static_evals(
+            pl.col("a"), eval_points=[-3.0, 0.0, 3.0], bandwidth=bandwidth
+        ),
+    )
+
+    assert df_kde["cv"][0][1] < df_kde["silverman"][0][1]
+
+
+def test_cv_bounds(sample_df, eval_points):
+    df_kde = sample_df.group_by("id").agg(
+        bounded=pkde.kde(
+            pl.col("a"),
+            eval_points=eval_points,
+            bandwidth="likelihood_cv",
+            cv_bounds=(0.5, 0.5000001),
+        ),
+        fixed=pkde.kde(pl.col("a"), eval_points=eval_points, bandwidth=0.5),
+    )
+
+    for bounded, fixed in zip(df_kde["bounded"], df_kde["fixed"]):
+        assert bounded.to_list() == pytest.approx(fixed.to_list(), rel=1e-4)
+
+
+def test_invalid_cv_bounds(sample_df, eval_points):
+    with pytest.raises(pl.exceptions.ComputeError):
+        sample_df.group_by("id").agg(
+            kde=pkde.kde(
+                pl.col("a"),
+                eval_points=eval_points,
+                bandwidth="lscv",
+                cv_bounds=(1.0, 0.5),
+            )
+        )
+
+
+def test_float64(sample_df, eval_points):
+    df = sample_df.with_columns(pl.col("a").cast(pl.Float64))
+    df_list = df.group_by("id").agg(pl.col("a"))
+
+    df_agg = df.group_by("id").agg(kde=pkde.kde(pl.col("a"), eval_points=eval_points))
+    df_static = df_list.with_columns(
+        kde=pkde.kde_static_evals(pl.col("a"), eval_points=eval_points)
+    )
+    df_dynamic = df_list.with_columns(
+        kde=pkde.kde_dynamic_evals(pl.col("a"), pl.col("a"))
+    )
+
+    assert df_agg.select("kde").dtypes[0] == pl.Array(pl.Float64, 5)
+    assert df_static.select("kde").dtypes[0] == pl.Array(pl.Float64, 5)
+    assert df_dynamic.select("kde").dtypes[0] == pl.List(pl.Float64)
+
+
+def test_float64_precision():
+    offset = 1e9
+    df = pl.DataFrame(
+        {"a": [offset, offset + 1.0, offset + 2.0]}, schema={"a": pl.Float64}
+    )
+
+    df_kde = df.select(
+        kde=pkde.kde(pl.col("a"), eval_points=[offset + 1.0], bandwidth=1.0)
+    )
+
+    expected = (2 * math.exp(-0.5) + 1) / (3 * math.sqrt(2 * math.pi))
+    assert df_kde["kde"].explode()[0] == pytest.approx(expected, rel=1e-9)
+
+
+@pytest.mark.parametrize(
+    ("dtype", "expected"),
+    [
+        (pl.Float32, pl.Float32),
+        (pl.Float64, pl.Float64),
+        (pl.Int8, pl.Float64),
+        (pl.Int64, pl.Float64),
+        (pl.UInt32, pl.Float64),
+        (pl.Decimal(10, 2), pl.Float64),
+    ],
+)
+def test_numeric_inputs(sample_df, eval_points, dtype, expected):
+    df = sample_df.with_columns(pl.col("a").cast(dtype))
+    df_list = df.group_by("id").agg(pl.col("a"))
+
+    df_agg = df.group_by("id").agg(kde=pkde.kde(pl.col("a"), eval_points=eval_points))
+    df_static = df_list.with_columns(
+        kde=pkde.kde_static_evals(pl.col("a"), eval_points=eval_points)
+    )
+    df_dynamic = df_list.with_columns(
+        kde=pkde.kde_dynamic_evals(pl.col("a"), pl.col("a"))
+    )
+
+    assert df_agg.select("kde").dtypes[0] == pl.Array(expected, 5)
+    assert df_static.select("kde").dtypes[0] == pl.Array(expected, 5)
+    assert df_dynamic.select("kde").dtypes[0] == pl.List(expected)
+
+
+def test_non_numeric_input(eval_points):
+    df = pl.DataFrame({"a": ["x", "y", "z"]})
+
+    with pytest.raises(pl.exceptions.ComputeError):
+        df.select(kde=pkde.kde(pl.col("a"), eval_points=eval_points))
+
+
+def test_duration_input():
+    df = pl.DataFrame(
+        {"a": [timedelta(seconds=1), timedelta(seconds=2)]},
+        schema={"a": pl.Duration("ms")},
+    )
+
+    df_kde = df.select(
+        kde=pkde.kde(
+            pl.col("a"),
+            eval_points=[timedelta(milliseconds=1500)],
+            bandwidth=timedelta(milliseconds=500),
+        )
+    )
+
+    # the density is per nanosecond, independent of the time unit of the column
+    expected = 2 * math.exp(-0.5) / (2 * 0.5e9 * math.sqrt(2 * math.pi))
+    assert df_kde.dtypes[0] == pl.Array(pl.Float64, 1)
+    assert df_kde["kde"].explode()[0] == pytest.approx(expected, rel=1e-9)
+
+
+@pytest.mark.parametrize(
+    "kwargs",
+    [
+        {"eval_points": [1.5e9]},
+        {"eval_points": [timedelta(seconds=1)], "bandwidth": 5e8},
+        {"eval_points": [timedelta(seconds=1)], "lower": 0.0},
+    ],
+)
+def test_temporal_input_rejects_plain_numbers(kwargs):
+    df = pl.DataFrame(
+        {"a": [timedelta(seconds=1), timedelta(seconds=2)]},
+        schema={"a": pl.Duration("ms")},
+    )
+
+    # plain numbers would be read in nanoseconds, whatever the unit of the column
+    with pytest.raises(pl.exceptions.ComputeError, match="timedeltas"):
+        df.select(kde=pkde.kde(pl.col("a"), **kwargs))
+
+
+def test_numeric_input_rejects_temporal_values(sample_df):
+    with pytest.raises(pl.exceptions.ComputeError, match="plain numbers"):
+        sample_df.select(
+            kde=pkde.kde(pl.col("a"), eval_points=[1.0], bandwidth=timedelta(seconds=1))
+        )
+    with pytest.raises(pl.exceptions.ComputeError, match="plain numbers"):
+        sample_df.select(kde=pkde.kde(pl.col("a"), eval_points=[timedelta(seconds=1)]))
+
+
+def test_mixed_temporal_values():
+    with pytest.raises(TypeError, match="mixes"):
+        pkde.kde(pl.col("a"), eval_points=[timedelta(seconds=1), 1.0])
+
+
+@pytest.mark.parametrize(
+    ("values", "eval_points", "dtype"),
+    [
+        (
+            [date(2024, 1, 1), date(2024, 1, 3), date(2024, 1, 4)],
+            [date(2024, 1, 2)],
+            pl.Date,
+        ),
+        (
+            [datetime(2024, 1, 1, 12), datetime(2024, 1, 1, 13), datetime(2024, 1, 2)],
+            [datetime(2024, 1, 1, 18)],
+            pl.Datetime("us"),
+        ),
+        (
+            [time(8), time(9, 30), time(12)],
+            [time(10)],
+            pl.Time,
+        ),
+    ],
+)
+def test_temporal_inputs(values, eval_points, dtype):
+    df = pl.DataFrame({"a": values}, schema={"a": dtype})
+
+    df_agg = df.select(kde=pkde.kde(pl.col("a"), eval_points=eval_points))
+    df_static = df.select(
+        kde=pkde.kde_static_evals(pl.col("a").implode(), eval_points=eval_points)
+    )
+    df_dynamic = df.select(
+        kde=pkde.kde_dynamic_evals(
+            pl.col("a").implode(), pl.lit(pl.Series(eval_points, dtype=dtype)).implode()
+        )
+    )
+
+    assert df_agg.dtypes[0] == pl.Array(pl.Float64, 1)
+    assert df_agg["kde"][0][0] > 0
+    assert df_static["kde"][0].to_list() == pytest.approx(df_agg["kde"][0].to_list())
+    assert df_dynamic["kde"][0].to_list() == pytest.approx(df_agg["kde"][0].to_list())
+
+
+@pytest.fixture
+def null_df() -> pl.DataFrame:
+    return pl.DataFrame(
+        {
+            "a": [1.0, None, 3.0, 4.0, 5.0],
+            "id": [0, 0, 1, 1, 1],
+        },
+        schema={"a": pl.Float32, "id": pl.Int64},
+    )
+
+
+def test_null_policy_drop(null_df, sample_df, eval_points):
+    df_kde = null_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, bandwidth=1.0)
+    )
+    df_expected = (
+        sample_df.filter(pl.col("a") != 2.0)
+        .group_by("id", maintain_order=True)
+        .agg(kde=pkde.kde(pl.col("a"), eval_points=eval_points, bandwidth=1.0))
+    )
+
+    assert_series_equal(df_kde["kde"], df_expected["kde"])
+
+
+def test_null_policy_propagate(null_df, eval_points):
+    df_kde = null_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, null_policy="propagate")
+    )
+
+    assert df_kde["kde"][0].null_count() == len(eval_points)
+    assert df_kde["kde"][1].null_count() == 0
+
+
+def test_null_policy_error(null_df, eval_points):
+    with pytest.raises(pl.exceptions.ComputeError, match="null"):
+        null_df.group_by("id").agg(
+            kde=pkde.kde(pl.col("a"), eval_points=eval_points, null_policy="error")
+        )
+
+
+def test_null_static_eval_points(sample_df):
+    df_kde = sample_df.group_by("id").agg(
+        kde=pkde.kde(pl.col("a"), eval_points=[1.0, None, 3.0])
+    )
+
+    assert (df_kde["kde"].arr.get(1).is_null()).all()
+    assert (df_kde["kde"].arr.get(0).is_not_null()).all()
+
+    with pytest.raises(pl.exceptions.ComputeError, match="null"):
+        sample_df.select(
+            kde=pkde.kde_static_evals(
+                pl.col("a").implode(), eval_points=[None], null_policy="error"
+            )
+        )
+
+
+def test_null_rows(eval_points):
+    df = pl.DataFrame(
+        {
+            "a": [[1.0, 2.0, None], None, [4.0, 5.0]],
+            "eval_points": [[1.0, None], [1.0], None],
+        },
+        schema={"a": pl.List(pl.Float64), "eval_points": pl.List(pl.Float64)},
+    )
+
+    df_kde = df.with_columns(
+        static=pkde.kde_static_evals(pl.col("a"), eval_points=eval_points),
+        dynamic=pkde.kde_dynamic_evals(pl.col("a"), pl.col("eval_points")),
+    )
+
+    assert df_kde["static"].is_null().to_list() == [False, True, False]
+    assert df_kde["dynamic"].is_null().to_list() == [False, True, True]
+    assert df_kde["dynamic"][0].is_null().to_list() == [False, True]
+
+    with pytest.raises(pl.exceptions.ComputeError, match="row 0"):
+        df.with_columns(
+            static=pkde.kde_static_evals(
+                pl.col("a"), eval_points=eval_points, null_policy="error"
+            )
+        )
+
+
+@pytest.fixture
+def nan_df() -> pl.DataFrame:
+    return pl.DataFrame(
+        {
+            "a": [1.0, float("nan"), 3.0, 4.0, float("inf"), 5.0],
+            "id": [0, 0, 0, 1, 1, 1],
+        },
+        schema={"a": pl.Float64, "id": pl.Int64},
+    )
+
+
+def test_nan_policy_drop(nan_df, eval_points):
+    df_kde = nan_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points)
+    )
+    df_expected = (
+        nan_df.filter(pl.col("a").is_finite())
+        .group_by("id", maintain_order=True)
+        .agg(kde=pkde.kde(pl.col("a"), eval_points=eval_points))
+    )
+
+    assert_series_equal(df_kde["kde"], df_expected["kde"])
+    assert not df_kde["kde"].explode().is_nan().any()
+
+
+def test_nan_policy_null(nan_df, eval_points):
+    df_kde = nan_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, nan_policy="null")
+    )
+
+    assert df_kde["kde"].explode().is_null().all()
+
+
+def test_nan_policy_error(nan_df, eval_points):
+    df = nan_df.group_by("id", maintain_order=True).agg(pl.col("a"))
+
+    with pytest.raises(pl.exceptions.ComputeError, match="NaN .* at row 0"):
+        df.with_columns(
+            kde=pkde.kde_static_evals(
+                pl.col("a"), eval_points=eval_points, nan_policy="error"
+            )
+        )
+
+
+def test_nan_policy_error_names_group(nan_df, eval_points):
+    df = nan_df.filter(pl.col("id") == 1)
+
+    # the group keys are not available to the plugin, so the group shows its samples
+    match = r"inf .* group of 3 sample points \[4, inf, 5\]"
+    with pytest.raises(pl.exceptions.ComputeError, match=match):
+        df.group_by("id").agg(
+            kde=pkde.kde(pl.col("a"), eval_points=eval_points, nan_policy="error")
+        )
+
+
+def test_non_finite_eval_points(sample_df):
+    df_kde = sample_df.select(
+        kde=pkde.kde(pl.col("a"), eval_points=[1.0, float("nan"), float("inf")])
+    )
+
+    assert df_kde["kde"].explode().is_null().to_list() == [False, True, True]
+
+    with pytest.raises(pl.exceptions.ComputeError, match="eval_points"):
+        sample_df.select(
+            kde=pkde.kde(
+                pl.col("a"), eval_points=[1.0, float("nan")], nan_policy="error"
+            )
+        )
+
+
+def test_weights(sample_df, eval_points):
+    weights = [1, 3, 2, 1, 2]
+    df = sample_df.with_columns(w=pl.Series(weights))
+    df_repeated = df.select(pl.col("a", "id").repeat_by("w").explode())
+
+    df_kde = df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, weights="w", bandwidth=0.5)
+    )
+    df_expected = df_repeated.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, bandwidth=0.5)
+    )
+    assert_series_equal(df_kde["kde"], df_expected["kde"], rtol=1e-5)
+
+    df_lists = df.group_by("id", maintain_order=True).agg(pl.col("a", "w"))
+    df_static = df_lists.select(
+        kde=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, weights="w", bandwidth=0.5
+        )
+    )
+    df_dynamic = df_lists.with_columns(e=pl.lit(eval_points)).select(
+        kde=pkde.kde_dynamic_evals(pl.col("a"), pl.col("e"), weights="w", bandwidth=0.5)
+    )
+    assert_series_equal(df_static["kde"], df_expected["kde"], rtol=1e-5)
+    assert_series_equal(df_dynamic["kde"], df_expected["kde"].arr.to_list(), rtol=1e-5)
+
+
+def test_weights_bandwidth():
+    rng = random.Random(1)
+    values = [rng.gauss(0.0, 1.0) for _ in range(50)]
+    df = pl.DataFrame({"a": values, "w": [rng.randint(1, 4) for _ in values]})
+    df_repeated = df.select(pl.col("a").repeat_by("w").explode())
+    x = [-1.0, 0.0, 1.0]
+
+    # the improved Sheather-Jones selector only sees the weights in its histogram
+    isj = df.select(
+        kde=pkde.kde(pl.col("a"), eval_points=x, weights="w", bandwidth="isj")
+    )
+    isj_repeated = df_repeated.select(
+        kde=pkde.kde(pl.col("a"), eval_points=x, bandwidth="isj")
+    )
+    assert isj["kde"][0].to_list() == pytest.approx(isj_repeated["kde"][0].to_list())
+
+    # the rules of thumb treat weights as reliability weights, so that counts give a
+    # smaller effective sample size and a wider bandwidth than the repeated samples
+    silverman = df.select(kde=pkde.kde(pl.col("a"), eval_points=[0.0], weights="w"))
+    silverman_repeated = df_repeated.select(
+        kde=pkde.kde(pl.col("a"), eval_points=[0.0])
+    )
+    assert silverman["kde"][0][0] != pytest.approx(silverman_repeated["kde"][0][0])
+
+
+def test_invalid_weights(sample_df, eval_points):
+    df = sample_df.with_columns(w=pl.Series([1.0, -1.0, 1.0, 1.0, 1.0]))
+
+    with pytest.raises(pl.exceptions.ComputeError, match="non-negative"):
+        df.select(kde=pkde.kde(pl.col("a"), eval_points=eval_points, weights="w"))
+
+    with pytest.raises(pl.exceptions.ComputeError, match="weights"):
+        df.with_columns(w=pl.lit("x")).select(
+            kde=pkde.kde(pl.col("a"), eval_points=eval_points, weights="w")
+        )
+
+
+@pytest.fixture
+def df_2d() -> pl.DataFrame:
+    rng = random.Random(0)
+    xs = [rng.gauss(0.0, 1.0) for _ in range(200)]
+    return pl.DataFrame(
+        {
+            "x": xs,
+            "y": [0.8 * x + 0.6 * rng.gauss(0.0, 1.0) for x in xs],
+            "id": [i % 2 for i in range(200)],
+        }
+    )
+
+
+@pytest.mark.parametrize("bandwidth_matrix", ["full", "diagonal"])
+def test_kde2d(df_2d, bandwidth_matrix):
+    grid = [-6.0 + 0.25 * i for i in range(49)]
+    df_kde = df_2d.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde2d(
+            "x", "y", grid_x=grid, grid_y=grid, bandwidth_matrix=bandwidth_matrix
+        )
+    )
+
+    assert df_kde["kde"].dtype == pl.List(pl.Float64)
+    assert df_kde["kde"].list.len().to_list() == [49 * 49, 49 * 49]
+    for density in df_kde["kde"]:
+        assert density.sum() * 0.25**2 == pytest.approx(1.0, abs=1e-3)
+
+
+def test_kde2d_correlation(df_2d):
+    # the full covariance puts more mass on the diagonal of positively correlated data
+    densities = {
+        matrix: df_2d.select(
+            kde=pkde.kde2d(
+                "x", "y", grid_x=[1.0, -1.0], grid_y=[1.0], bandwidth_matrix=matrix
+            )
+        )["kde"]
+        for matrix in ("full", "diagonal")
+    }
+
+    assert densities["full"][0] > densities["diagonal"][0]
+    assert densities["full"][1] < densities["diagonal"][1]
+
+
+def test_kde2d_array_output(df_2d):
+    df_kde = df_2d.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde2d("x", "y", grid_x=[0.0, 1.0, 2.0], grid_y=[0.0, 1.0])
+    )
+    df_array = df_2d.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde2d(
+            "x", "y", grid_x=[0.0, 1.0, 2.0], grid_y=[0.0, 1.0], output="array"
+        )
+    )
+
+    assert df_array["kde"].dtype == pl.Array(pl.Float64, (3, 2))
+    assert_series_equal(
+        df_array["kde"].arr.explode().arr.explode().implode(),
+        df_kde["kde"].explode().implode(),
+        check_names=False,
+    )
+
+
+@pytest.mark.parametrize("bandwidth_matrix", ["full", "diagonal", "scaled_identity"])
+def test_kde_nd(df_2d, bandwidth_matrix):
+    grid = [-1.0, 0.0, 1.5]
+    points = [[x, y] for x in grid for y in grid]
+    options = {"bandwidth_matrix": bandwidth_matrix, "bandwidth": "silverman"}
+
+    df_kde = df_2d.group_by("id", maintain_order=True).agg(
+        array=pkde.kde_nd(
+            pl.concat_list("x", "y").list.to_array(2), eval_points=points, **options
+        ),
+        struct=pkde.kde_nd(
+            pl.struct("x", "y"),
+            eval_points=[{"y": y, "x": x} for x, y in points],
+            **options,
+        ),
+        grid=pkde.kde2d("x", "y", grid_x=grid, grid_y=grid, **options),
+    )
+
+    assert df_kde["array"].dtype == pl.List(pl.Float64)
+    assert_series_equal(df_kde["array"], df_kde["grid"], check_names=False)
+    assert_series_equal(df_kde["struct"], df_kde["grid"], check_names=False)
+
+
+def test_kde_nd_series_eval_points(df_2d):
+    points = pl.DataFrame({"x": [-1.0, 0.0, 1.5], "y": [0.0, 1.0, -1.0]})
+    arrays = points.select(pl.concat_list("x", "y").list.to_array(2)).to_series()
+    structs = points.select(pl.struct("x", "y")).to_series()
+
+    df_kde = df_2d.group_by("id", maintain_order=True).agg(
+        array=pkde.kde_nd(
+            pl.concat_list("x", "y").list.to_array(2), eval_points=arrays
+        ),
+        struct=pkde.kde_nd(pl.struct("x", "y"), eval_points=structs),
+        expected=pkde.kde_nd(pl.struct("x", "y"), eval_points=points.rows()),
+    )
+
+    assert_series_equal(df_kde["array"], df_kde["expected"], check_names=False)
+    assert_series_equal(df_kde["struct"], df_kde["expected"], check_names=False)
+
+
+def test_kde_nd_one_dimension(sample_df, eval_points):
+    df_kde = sample_df.group_by("id", maintain_order=True).agg(
+        nd=pkde.kde_nd(
+            pl.concat_list("a").list.to_array(1),
+            eval_points=[[x] for x in eval_points],
+            bandwidth=0.5,
+        ),
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, bandwidth=0.5),
+    )
+
+    assert df_kde["nd"].dtype == pl.List(pl.Float32)
+    assert_series_equal(df_kde["nd"], df_kde["kde"].arr.to_list(), check_names=False)
+
+
+def test_kde_nd_invalid_eval_points(df_2d):
+    values = pl.concat_list("x", "y").list.to_array(2)
+
+    with pytest.raises(pl.exceptions.ComputeError, match="2 coordinates"):
+        df_2d.select(kde=pkde.kde_nd(values, eval_points=[[0.0, 0.0, 0.0]]))
+
+    with pytest.raises(pl.exceptions.ComputeError, match="unknown field `z`"):
+        df_2d.select(kde=pkde.kde_nd(pl.struct("x", "y"), eval_points=[{"z": 0.0}]))
+
+
+@pytest.mark.parametrize("kernel", ["gaussian", "epanechnikov", "logistic"])
+def test_fft_method(bimodal_df, kernel):
+    eval_points = [-4.0 + 0.5 * i for i in range(17)]
+    df_kde = bimodal_df.with_columns(w=pl.lit(1.0) + (pl.col("a") > 0)).select(
+        exact=pkde.kde(
+            pl.col("a"), eval_points=eval_points, kernel=kernel, weights="w"
+        ),
+        fft=pkde.kde(
+            pl.col("a"),
+            eval_points=eval_points,
+            kernel=kernel,
+            weights="w",
+            method="fft",
+        ),
+    )
+
+    assert_series_equal(
+        df_kde["fft"].explode(),
+        df_kde["exact"].explode(),
+        check_names=False,
+        rtol=1e-3,
+        atol=1e-4,
+    )
+
+
+@pytest.mark.parametrize("outlier", [1e3, 1e7])
+def test_fft_method_outlier(bimodal_df, outlier):
+    # the grid must still resolve the bandwidth when an outlier stretches the range
+    eval_points = [-4.0 + 0.5 * i for i in range(17)] + [outlier]
+    df = pl.DataFrame({"a": [*bimodal_df["a"].cast(pl.Float64), outlier]})
+    df_kde = df.select(
+        **{
+            f"{estimate.__name__}_{method}": estimate(
+                pl.col("a"), eval_points=eval_points, bandwidth=0.3, method=method
+            )
+            for estimate in (pkde.kde, pkde.kde_cdf)
+            for method in ("exact", "fft")
+        }
+    )
+
+    for estimate in ("kde", "kde_cdf"):
+        assert_series_equal(
+            df_kde[f"{estimate}_fft"].explode(),
+            df_kde[f"{estimate}_exact"].explode(),
+            check_names=False,
+            rtol=1e-3,
+            atol=1e-4,
+        )
+
+
+@pytest.mark.parametrize("kernel", ["gaussian", "epanechnikov", "logistic"])
+def test_tree_method(bimodal_df, kernel):
+    df = bimodal_df.select(a=pl.col("a").cast(pl.Float64)).select(
+        a=pl.col("a").implode(), e=pl.col("a").implode()
+    )
+    exact, exact_tree, approx = (
+        df.select(
+            kde=pkde.kde_dynamic_evals(
+                pl.col("a"), pl.col("e"), kernel=kernel, **options
+            )
+        )["kde"].explode()
+        for options in (
+            {},
+            {"method": "tree"},
+            {"method": "tree", "rtol": 1e-3, "atol": 1e-6},
+        )
+    )
+
+    assert_series_equal(exact_tree, exact, rtol=1e-12)
+    assert ((approx - exact).abs() <= 1e-6 + 1e-3 * exact).all()
+
+
+def test_invalid_tolerance(sample_df, eval_points):
+    with pytest.raises(pl.exceptions.ComputeError, match="rtol"):
+        sample_df.select(
+            kde=pkde.kde(pl.col("a"), eval_points=eval_points, method="tree", rtol=-1.0)
+        )
+
+
+def test_generated_grid(sample_df):
+    df_kde = sample_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), n_points=11, cut=2.0, bandwidth=0.5)
+    )
+
+    assert df_kde["kde"].dtype == pl.List(
+        pl.Struct({"x": pl.Float32, "density": pl.Float32})
+    )
+    grid = df_kde["kde"].explode().struct.unnest()
+    assert grid["x"].to_list()[:11] == pytest.approx([0.0 + 0.3 * i for i in range(11)])
+    assert grid["x"].to_list()[11:] == pytest.approx([2.0 + 0.4 * i for i in range(11)])
+
+    df_expected = sample_df.filter(pl.col("id") == 0).select(
+        kde=pkde.kde(pl.col("a"), eval_points=grid["x"][:11].to_list(), bandwidth=0.5)
+    )
+    assert_series_equal(
+        grid["density"][:11], df_expected["kde"].explode(), check_names=False, rtol=1e-5
+    )
+
+
+def test_global_grid(sample_df):
+    df = sample_df.group_by("id", maintain_order=True).agg(pl.col("a"))
+    df_kde = df.select(
+        local=pkde.kde_static_evals(pl.col("a"), n_points=5, cut=0.0),
+        global_=pkde.kde_static_evals(pl.col("a"), n_points=5, cut=0.0, grid="global"),
+    )
+
+    local = df_kde["local"].list.eval(pl.element().struct.field("x")).to_list()
+    global_ = df_kde["global_"].list.eval(pl.element().struct.field("x")).to_list()
+    assert local == [[1.0, 1.25, 1.5, 1.75, 2.0], [3.0, 3.5, 4.0, 4.5, 5.0]]
+    assert global_ == [[1.0, 2.0, 3.0, 4.0, 5.0]] * 2
+
+    with pytest.raises(pl.exceptions.ComputeError, match="global"):
+        sample_df.group_by("id").agg(kde=pkde.kde(pl.col("a"), grid="global"))
+
+
+def test_generated_grid_temporal():
+    df = pl.DataFrame({"a": [date(2024, 1, 1), date(2024, 1, 3), date(2024, 1, 5)]})
+    df_kde = df.select(kde=pkde.kde(pl.col("a"), n_points=9, cut=0.0))
+
+    # the grid points fall between days, so they are returned as datetimes
+    assert df_kde["kde"].dtype == pl.Struct(
+        {"x": pl.Datetime("ns"), "density": pl.Float64}
+    )
+    assert df_kde["kde"].struct.field("x").to_list() == [
+        datetime(2024, 1, 1) + i * timedelta(hours=12) for i in range(9)
+    ]
+
+
+def test_generated_grid_time():
+    df = pl.DataFrame({"a": [time(0, 30), time(12), time(23, 30)]})
+    df_kde = df.select(kde=pkde.kde(pl.col("a"), n_points=50, cut=3.0))
+    x = df_kde["kde"].struct.field("x")
+
+    assert x.dtype == pl.Time
+    assert x.is_sorted()
+    assert x.n_unique() == 50
+    assert x.first() == time(0)
+
+
+def test_output(sample_df, eval_points):
+    df_agg = sample_df.group_by("id", maintain_order=True).agg(
+        density=pkde.kde(pl.col("a"), eval_points=eval_points),
+        points=pkde.kde(pl.col("a"), eval_points=eval_points, output="points"),
+        columns=pkde.kde(pl.col("a"), eval_points=eval_points, output="columns"),
+    )
+    assert df_agg["points"].dtype == pl.List(
+        pl.Struct({"x": pl.Float32, "density": pl.Float32})
+    )
+    assert df_agg["columns"].dtype == pl.Struct(
+        {"x": pl.List(pl.Float32), "density": pl.List(pl.Float32)}
+    )
+    assert df_agg["points"].list.eval(pl.element().struct.field("x")).to_list() == [
+        eval_points
+    ] * 2
+    densities = df_agg["density"].arr.to_list()
+    assert_series_equal(
+        df_agg["points"].list.eval(pl.element().struct.field("density")),
+        densities,
+        check_names=False,
+    )
+    assert df_agg["columns"].struct.field("x").to_list() == [eval_points] * 2
+    assert_series_equal(
+        df_agg["columns"].struct.field("density"), densities, check_names=False
+    )
+
+    df = sample_df.group_by("id", maintain_order=True).agg(pl.col("a"))
+    df_static = df.select(
+        points=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, output="points"
+        ),
+        columns=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, output="columns"
+        ),
+    )
+    assert df_static["points"].equals(df_agg["points"])
+    assert df_static["columns"].equals(df_agg["columns"])
+
+    df_dynamic = df.with_columns(e=pl.lit(eval_points)).select(
+        points=pkde.kde_dynamic_evals(pl.col("a"), pl.col("e"), output="points"),
+        columns=pkde.kde_dynamic_evals(pl.col("a"), pl.col("e"), output="columns"),
+    )
+    assert df_dynamic["points"].equals(df_agg["points"])
+    assert df_dynamic["columns"].equals(df_agg["columns"])
+
+
+def test_generated_grid_density_output(sample_df):
+    df_kde = sample_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde(pl.col("a"), n_points=11, output="density")
+    )
+
+    assert df_kde["kde"].dtype == pl.Array(pl.Float32, 11)
+
+
+@pytest.mark.parametrize(
+    ("output", "dtype"),
+    [
+        ("points", pl.List(pl.Struct({"x": pl.Float32, "density": pl.Float32}))),
+        (
+            "columns",
+            pl.Struct({"x": pl.List(pl.Float32), "density": pl.List(pl.Float32)}),
+        ),
+    ],
+)
+def test_output_schema(sample_df, eval_points, output, dtype):
+    lf = sample_df.lazy()
+    lf_lists = lf.group_by("id", maintain_order=True).agg(pl.col("a"))
+    queries = [
+        lf.group_by("id", maintain_order=True).agg(
+            kde=pkde.kde(pl.col("a"), eval_points=eval_points, output=output),
+            grid=pkde.kde(pl.col("a"), n_points=3, output=output),
+        ),
+        lf_lists.with_columns(
+            kde=pkde.kde_static_evals(
+                pl.col("a"), eval_points=eval_points, output=output
+            ),
+            grid=pkde.kde_static_evals(pl.col("a"), n_points=3, output=output),
+            dynamic=pkde.kde_dynamic_evals(
+                pl.col("a"), pl.lit(eval_points), output=output
+            ),
+        ),
+    ]
+
+    for query in queries:
+        schema = query.collect_schema()
+        assert schema == query.collect().schema
+        assert all(schema[name] == dtype for name in schema if name not in ("id", "a"))
+
+
+@pytest.mark.parametrize("output", ["density", "array"])
+def test_array_output_schema(sample_df, eval_points, output):
+    lf = sample_df.lazy()
+    lf_lists = lf.group_by("id", maintain_order=True).agg(pl.col("a"))
+    queries = [
+        lf.group_by("id", maintain_order=True).agg(
+            kde=pkde.kde(pl.col("a"), eval_points=eval_points, output=output),
+            grid=pkde.kde(pl.col("a"), n_points=3, output=output),
+        ),
+        lf_lists.with_columns(
+            kde=pkde.kde_static_evals(
+                pl.col("a"), eval_points=eval_points, output=output
+            ),
+            grid=pkde.kde_static_evals(pl.col("a"), n_points=3, output=output),
+        ),
+    ]
+
+    # the number of evaluation points is known before evaluation
+    for query in queries:
+        schema = query.collect_schema()
+        assert schema == query.collect().schema
+        assert schema["kde"] == pl.Array(pl.Float32, len(eval_points))
+        assert schema["grid"] == pl.Array(pl.Float32, 3)
+
+
+def test_dynamic_output_schema(sample_df, eval_points):
+    query = (
+        sample_df.lazy()
+        .group_by("id", maintain_order=True)
+        .agg(pl.col("a"))
+        .with_columns(kde=pkde.kde_dynamic_evals(pl.col("a"), pl.lit(eval_points)))
+    )
+    schema = query.collect_schema()
+
+    # the rows may have different numbers of evaluation points
+    assert schema == query.collect().schema
+    assert schema["kde"] == pl.List(pl.Float32)
+
+
+def test_multivariate_output_schema(df_2d):
+    query = df_2d.lazy().group_by("id").agg(
+        kde2d=pkde.kde2d("x", "y", grid_x=[0.0, 1.0], grid_y=[0.0, 1.0, 2.0]),
+        array=pkde.kde2d(
+            "x", "y", grid_x=[0.0, 1.0], grid_y=[0.0, 1.0, 2.0], output="array"
+        ),
+        kde_nd=pkde.kde_nd(pl.struct("x", "y"), eval_points=[[0.0, 0.0]]),
+    )
+
+    assert query.collect_schema() == query.collect().schema
+    assert query.collect_schema()["array"] == pl.Array(pl.Float64, (2, 3))
+
+
+def test_array_output(sample_df, eval_points):
+    df_agg = sample_df.group_by("id", maintain_order=True).agg(
+        density=pkde.kde(pl.col("a"), eval_points=eval_points),
+        array=pkde.kde(pl.col("a"), eval_points=eval_points, output="array"),
+        grid=pkde.kde(pl.col("a"), n_points=7, output="array"),
+    )
+    df = sample_df.group_by("id", maintain_order=True).agg(pl.col("a"))
+    df_static = df.select(
+        array=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, output="array"
+        )
+    )
+
+    assert df_agg["array"].dtype == pl.Array(pl.Float32, 5)
+    assert df_agg["grid"].dtype == pl.Array(pl.Float32, 7)
+    assert_series_equal(df_agg["array"], df_agg["density"], check_names=False)
+    assert df_static["array"].equals(df_agg["array"])
+
+    np = pytest.importorskip("numpy")
+    assert isinstance(df_static["array"].to_numpy(), np.ndarray)
+    assert df_static["array"].to_numpy().shape == (2, 5)
+
+    with pytest.raises(pl.exceptions.ComputeError, match="array"):
+        df.with_columns(e=pl.lit(eval_points)).select(
+            pkde.kde_dynamic_evals(pl.col("a"), pl.col("e"), output="array")
+        )
+
+
+@pytest.mark.parametrize("kernel", ["gaussian", "epanechnikov", "triangular", "cosine"])
+def test_kde_cdf(bimodal_df, kernel):
+    df = bimodal_df.select(pl.col("a").cast(pl.Float64))
+    grid = [-8.0 + 0.01 * i for i in range(1601)]
+    df_kde = df.select(
+        density=pkde.kde(pl.col("a"), eval_points=grid, kernel=kernel),
+        cdf=pkde.kde_cdf(pl.col("a"), eval_points=grid, kernel=kernel),
+    )
+
+    # the CDF is the integral of the density, approximated by the trapezoidal rule
+    density = df_kde["density"].explode().to_list()
+    cdf = df_kde["cdf"].explode()
+    integral = [0.0]
+    for lo, hi in zip(density, density[1:]):
+        integral.append(integral[-1] + 0.005 * (lo + hi))
+    assert cdf.to_list() == pytest.approx(integral, abs=1e-4)
+    assert cdf[0] == pytest.approx(0.0, abs=1e-6)
+    assert cdf[-1] == pytest.approx(1.0, abs=1e-6)
+    assert cdf[800] == pytest.approx(0.5, abs=0.01)
+
+
+@pytest.mark.parametrize("method", ["fft", "tree"])
+def test_kde_cdf_methods(bimodal_df, method):
+    eval_points = [-5.0, -3.0, -1.0, 0.0, 1.0, 3.0, 5.0]
+    df = bimodal_df.select(pl.col("a").cast(pl.Float64), id=pl.lit(0)).group_by("id")
+    df_kde = df.agg(
+        exact=pkde.kde_cdf(pl.col("a"), eval_points=eval_points),
+        approx=pkde.kde_cdf(
+            pl.col("a"), eval_points=eval_points, method=method, rtol=1e-6
+        ),
+    )
+
+    assert df_kde["approx"].explode().to_list() == pytest.approx(
+        df_kde["exact"].explode().to_list(), abs=1e-4
+    )
+
+
+def test_kde_cdf_static_and_dynamic(sample_df, eval_points):
+    df = sample_df.group_by("id", maintain_order=True).agg(pl.col("a"))
+    df_agg = sample_df.group_by("id", maintain_order=True).agg(
+        kde=pkde.kde_cdf(pl.col("a"), eval_points=eval_points)
+    )
+    df_static = df.select(
+        kde=pkde.kde_cdf_static_evals(pl.col("a"), eval_points=eval_points)
+    )
+    df_dynamic = df.with_columns(e=pl.lit(eval_points)).select(
+        kde=pkde.kde_cdf_dynamic_evals(pl.col("a"), pl.col("e"))
+    )
+    df_points = df.select(
+        kde=pkde.kde_cdf_static_evals(pl.col("a"), n_points=5, cut=0.0)
+    )
+
+    assert_series_equal(df_static["kde"], df_agg["kde"])
+    assert_series_equal(df_dynamic["kde"], df_agg["kde"].arr.to_list())
+    assert df_points["kde"].dtype == pl.List(
+        pl.Struct({"x": pl.Float32, "cdf": pl.Float32})
+    )
+    for cdf in df_agg["kde"].to_list():
+        assert cdf == sorted(cdf)
+
+
+@pytest.mark.parametrize("kernel", ["gaussian", "epanechnikov"])
+def test_kde_quantile(sample_df, kernel):
+    levels = [0.1, 0.25, 0.5, 0.75, 0.9]
+    df_kde = sample_df.group_by("id", maintain_order=True).agg(
+        quantiles=pkde.kde_quantile(pl.col("a"), levels, kernel=kernel),
+        median=pkde.kde_quantile(pl.col("a"), 0.5, kernel=kernel),
+    )
+
+    assert df_kde["quantiles"].dtype == pl.List(pl.Float32)
+    assert df_kde["median"].dtype == pl.Float32
+    assert df_kde["median"].to_list() == pytest.approx([1.5, 4.0], abs=1e-5)
+
+    # the quantiles invert the CDF
+    for group, quantiles in zip([0, 1], df_kde["quantiles"].to_list()):
+        df_cdf = sample_df.filter(pl.col("id") == group).select(
+            cdf=pkde.kde_cdf(
+                pl.col("a").cast(pl.Float64), eval_points=quantiles, kernel=kernel
+            )
+        )
+        assert df_cdf["cdf"].explode().to_list() == pytest.approx(levels, abs=1e-5)
+
+
+@pytest.mark.parametrize("method", ["exact", "fft", "tree"])
+@pytest.mark.parametrize("support", [{"lower": 0.0}, {"period": 10.0}])
+def test_kde_quantile_bounded(method, support):
+    df = pl.DataFrame({"a": [0.2, 0.5, 1.0, 2.0, 3.5, 4.0, 7.0, 9.5]})
+    levels = [0.05, 0.25, 0.5, 0.75, 0.95]
+    options = {"method": method, **support}
+    quantiles = df.select(q=pkde.kde_quantile(pl.col("a"), levels, **options))["q"]
+    df_cdf = df.select(
+        cdf=pkde.kde_cdf(pl.col("a"), eval_points=quantiles.to_list()[0], **options)
+    )
+
+    assert df_cdf["cdf"].explode().to_list() == pytest.approx(levels, abs=1e-4)
+
+
+def test_kde_quantile_edge_cases():
+    df = pl.DataFrame({"a": [None, 1.0, 2.0, 3.0], "id": [0, 1, 2, 2]})
+    df_kde = df.group_by("id", maintain_order=True).agg(
+        median=pkde.kde_quantile(pl.col("a"), 0.5)
+    )
+    assert df_kde["median"].to_list() == [None, 1.0, pytest.approx(2.5)]
+
+    df_dates = pl.DataFrame({"a": [datetime(2024, 1, 1), datetime(2024, 1, 3)]})
+    df_median = df_dates.select(median=pkde.kde_quantile(pl.col("a"), 0.5))
+    assert df_median["median"].dtype == pl.Datetime("us")
+    assert abs(df_median["median"][0] - datetime(2024, 1, 2)) < timedelta(seconds=1)
+
+    with pytest.raises(pl.exceptions.ComputeError, match="quantiles"):
+        df.select(pkde.kde_quantile(pl.col("a"), 1.5))
+
+
+def test_kde_sample():
+    random.seed(0)
+    values = [random.gauss(10.0, 2.0) for _ in range(200)]
+    df = pl.DataFrame({"a": values * 2, "id": [0] * 200 + [1] * 200})
+    df_draws = df.group_by("id", maintain_order=True).agg(
+        draws=pkde.kde_sample(pl.col("a"), 5000, seed=42)
+    )
+
+    assert df_draws["draws"].dtype == pl.List(pl.Float64)
+    assert df_draws["draws"].list.len().to_list() == [5000, 5000]
+    first, second = df_draws["draws"].to_list()
+    # the draws are smoothed around the sample, so they repeat no sample point
+    assert not set(first) & set(values)
+    assert sum(first) / len(first) == pytest.approx(10.0, abs=0.3)
+    assert df_draws["draws"].list.std().to_list() == pytest.approx([2.0] * 2, rel=0.15)
+
+    # identical groups draw identical values from the same seed, and new ones otherwise
+    again = df.select(draws=pkde.kde_sample(pl.col("a").head(200), 5000, seed=42))
+    assert again["draws"].to_list() == first
+    other = df.select(draws=pkde.kde_sample(pl.col("a").head(200), 5000, seed=7))
+    assert other["draws"].to_list() != first
+
+
+def test_kde_sample_edge_cases():
+    df = pl.DataFrame({"a": [None, 1.0, 2.0, 3.0], "id": [0, 1, 2, 2]})
+    df_draws = df.group_by("id", maintain_order=True).agg(
+        draws=pkde.kde_sample(pl.col("a"), 3, seed=0, kernel="epanechnikov")
+    )
+    nulls, single, pair = df_draws["draws"].to_list()
+    assert nulls == [None] * 3
+    assert single == [1.0] * 3
+    assert len(pair) == 3
+
+    df_weighted = pl.DataFrame({"a": [0.0, 100.0], "w": [1.0, 0.0]})
+    df_draws = df_weighted.select(
+        pkde.kde_sample(pl.col("a"), 100, seed=0, weights=pl.col("w"))
+    )
+    assert df_draws["a"].to_list() == [0.0] * 100
+
+    df_dates = pl.DataFrame({"a": [datetime(2024, 1, 1), datetime(2024, 1, 3)]})
+    df_draws = df_dates.select(pkde.kde_sample(pl.col("a"), 10, seed=0))
+    assert df_draws["a"].dtype == pl.Datetime("us")
+
+
+@pytest.mark.parametrize(
+    "support",
+    [
+        {"lower": 0.0},
+        {"lower": 0.0, "boundary": "renormalization"},
+        {"lower": 0.0, "upper": 1.0},
+        {"period": 1.0},
+    ],
+)
+def test_kde_sample_support(support):
+    df = pl.DataFrame({"a": [0.05, 0.1, 0.2, 0.3, 0.5, 0.8, 0.9, 0.95]})
+    draws = df.select(
+        draws=pkde.kde_sample(pl.col("a"), 10000, seed=0, bandwidth=0.3, **support)
+    )["draws"].explode()
+    lower = support.get("lower", 0.0)
+    upper = support.get("upper", support.get("period", float("inf")))
+
+    assert draws.min() >= lower
+    assert draws.max() <= upper
+    # the draws follow the corrected density
+    cdf = df.select(
+        cdf=pkde.kde_cdf(pl.col("a"), eval_points=[0.25], bandwidth=0.3, **support)
+    )["cdf"].explode()[0]
+    assert (draws <= 0.25).mean() == pytest.approx(cdf, abs=0.02)
+
+
+@pytest.mark.parametrize("boundary", ["reflection", "renormalization"])
+@pytest.mark.parametrize("method", ["exact", "fft", "tree"])
+def test_boundary_correction(boundary, method):
+    random.seed(0)
+    df = pl.DataFrame({"a": [random.expovariate(1.0) for _ in range(200)]})
+    step = 0.01
+    eval_points = [-0.5] + [i * step for i in range(2001)]
+    df_kde = df.select(
+        plain=pkde.kde(pl.col("a"), eval_points=eval_points, method=method),
+        bounded=pkde.kde(
+            pl.col("a"),
+            eval_points=eval_points,
+            method=method,
+            lower=0.0,
+            boundary=boundary,
+        ),
+    )
+
+    def integral(densities):
+        return step * (sum(densities) - 0.5 * (densities[0] + densities[-1]))
+
+    plain, bounded = df_kde["plain"][0].to_list(), df_kde["bounded"][0].to_list()
+    # the plain estimate leaks mass below zero, the corrected one integrates to one
+    assert plain[0] > 0.0
+    assert integral(plain[1:]) < 0.95
+    assert bounded[0] == 0.0
+    assert integral(bounded[1:]) == pytest.approx(1.0, abs=1e-3)
+
+
+@pytest.mark.parametrize("boundary", ["reflection", "renormalization"])
+def test_boundary_correction_cdf(boundary):
+    df = pl.DataFrame({"a": [0.0, 0.1, 0.2, 0.5, 0.9, 1.0]})
+    kwargs = {"lower": 0.0, "upper": 1.0, "boundary": boundary}
+    eval_points = [-1.0, 0.0, 0.5, 1.0, 2.0]
+    cdf = df.select(pkde.kde_cdf(pl.col("a"), eval_points=eval_points, **kwargs))
+    cdf = cdf["a"].explode().to_list()
+    assert cdf[:2] == [0.0, 0.0]
+    assert cdf[2] == pytest.approx(0.5, abs=0.15)
+    assert cdf[3:] == pytest.approx([1.0, 1.0])
+
+    quantiles = df.select(pkde.kde_quantile(pl.col("a"), [0.0, 1.0], **kwargs))
+    assert quantiles["a"].to_list() == pytest.approx([0.0, 1.0], abs=1e-9)
+
+    # generated grids are clipped to the support
+    grid = df.select(pkde.kde(pl.col("a"), n_points=11, output="columns", **kwargs))
+    assert grid["a"].struct.field("x").to_list()[0] == pytest.approx(
+        [i / 10 for i in range(11)]
+    )
+
+    with pytest.raises(pl.exceptions.ComputeError, match="within the bounds"):
+        df.select(pkde.kde(pl.col("a"), eval_points=[0.5], lower=0.5))
+    with pytest.raises(pl.exceptions.ComputeError, match="less than `upper`"):
+        df.select(pkde.kde(pl.col("a"), eval_points=[0.5], lower=1.0, upper=0.0))
+
+
+def test_reflection_narrow_support():
+    # a bandwidth far wider than the support would take thousands of reflected images
+    df = pl.DataFrame({"a": [0.1, 0.2, 0.5, 0.6, 0.9]})
+    kwargs = {"lower": 0.0, "upper": 1.0, "bandwidth": 100.0}
+    eval_points = [i / 10 for i in range(11)]
+    df_kde = df.select(
+        kde=pkde.kde(pl.col("a"), eval_points=eval_points, **kwargs),
+        cdf=pkde.kde_cdf(pl.col("a"), eval_points=eval_points, **kwargs),
+    )
+
+    # the reflected kernels are flat over the support and still integrate to one
+    assert df_kde["kde"].explode().to_list() == pytest.approx([1.0] * 11, rel=1e-3)
+    assert df_kde["cdf"].explode().to_list() == pytest.approx(eval_points, abs=1e-3)
+
+
+@pytest.mark.parametrize("method", ["exact", "fft", "tree"])
+def test_periodic(method):
+    df = pl.DataFrame({"hour": [23.8, 0.1, 0.3, 0.6, 1.0, 1.5, 12.0]})
+    eval_points = [0.0, 24.0, 23.999, -0.001, 48.0, 12.0]
+    kwargs = {"bandwidth": 1.0, "method": method}
+    df_kde = df.select(
+        plain=pkde.kde(pl.col("hour"), eval_points=eval_points, **kwargs),
+        periodic=pkde.kde(pl.col("hour"), eval_points=eval_points, period=24, **kwargs),
+    )
+
+    plain, periodic = df_kde["plain"][0].to_list(), df_kde["periodic"][0].to_list()
+    # the plain density disagrees across midnight, the periodic one is continuous
+    assert plain[0] != pytest.approx(plain[1], rel=0.5)
+    assert periodic[:5] == pytest.approx([periodic[0]] * 5, rel=1e-3)
+
+    df_grid = df.select(
+        pkde.kde(pl.col("hour"), n_points=241, period=24, output="columns", **kwargs)
+    )
+    x, density = df_grid["hour"].struct.unnest().row(0)
+    assert x[0] == 0.0
+    assert x[-1] == pytest.approx(24.0)
+    step = x[1] - x[0]
+    integral = step * (sum(density) - 0.5 * (density[0] + density[-1]))
+    assert integral == pytest.approx(1.0, abs=1e-3)
+
+
+def test_periodic_static_evals():
+    df = pl.DataFrame(
+        {
+            "t": [time(23, 50), time(0, 5), time(0, 20), time(12, 0)],
+            "id": [0, 0, 0, 0],
+        }
+    )
+    df = df.group_by("id").agg(pl.col("t"))
+    df_kde = df.select(
+        pkde.kde_static_evals(
+            pl.col("t"),
+            eval_points=[time(23, 59, 59), time(0, 0)],
+            period=timedelta(days=1),
+        )
+    )
+    late, midnight = df_kde["t"].to_list()[0]
+    assert late == pytest.approx(midnight, rel=1e-3)
+
+    with pytest.raises(pl.exceptions.ComputeError, match="period"):
+        df.select(pkde.kde_static_evals(pl.col("t"), period=24, lower=0))
+
+
+@pytest.mark.parametrize("kernel", ["gaussian", "epanechnikov", "cosine", "logistic"])
+def test_kde_modes(kernel):
+    random.seed(0)
+    values = [random.gauss(0.0, 1.0) for _ in range(300)]
+    values += [random.gauss(8.0, 0.5) for _ in range(100)]
+    df = pl.DataFrame({"a": values})
+    df_modes = df.select(
+        modes=pkde.kde_modes(pl.col("a"), kernel=kernel, min_prominence=0.2)
+    )
+
+    assert df_modes["modes"].dtype == pl.Struct(
+        {"x": pl.Float64, "density": pl.Float64, "prominence": pl.Float64}
+    )
+    modes = df_modes["modes"].to_list()
+    assert [m["x"] for m in modes] == [
+        pytest.approx(0.0, abs=0.5),
+        pytest.approx(8.0, abs=0.5),
+    ]
+    # the heights are those of the estimator, and no evaluation point nearby is higher
+    for mode in modes:
+        nearby = [mode["x"] + d for d in (-1e-2, 0.0, 1e-2)]
+        density = df.select(pkde.kde(pl.col("a"), eval_points=nearby, kernel=kernel))
+        left, center, right = density["a"][0].to_list()
+        assert mode["density"] == pytest.approx(center)
+        assert center >= max(left, right)
+    assert modes[0]["prominence"] == modes[0]["density"]
+    assert 0.0 < modes[1]["prominence"] < modes[1]["density"]
+
+
+def test_kde_modes_groups():
+    df = pl.DataFrame(
+        {
+            "a": [None, 1.0, -1.0, 1.0, 0.0, 0.5, 1.0],
+            "id": [0, 1, 2, 2, 3, 3, 3],
+        }
+    )
+    df_modes = df.group_by("id", maintain_order=True).agg(
+        modes=pkde.kde_modes(pl.col("a"), bandwidth=0.5)
+    )
+    empty, single, pair, triple = df_modes["modes"].to_list()
+    assert empty == []
+    assert single == [{"x": 1.0, "density": 0.0, "prominence": 0.0}]
+    # two points with bandwidth 0.5 peak where x = tanh(4 x), i.e. just inside them
+    assert [m["x"] for m in pair] == pytest.approx([-0.99933, 0.99933], abs=1e-5)
+    assert len(triple) == 1
+
+    with pytest.raises(pl.exceptions.ComputeError, match="differentiable"):
+        df.select(pkde.kde_modes(pl.col("a"), kernel="uniform"))
+    with pytest.raises(pl.exceptions.ComputeError, match="min_prominence"):
+        df.select(pkde.kde_modes(pl.col("a"), min_prominence=2.0))
+
+
+@pytest.mark.parametrize("method", ["exact", "fft", "tree"])
+def test_log_density(sample_df, method):
+    eval_points = [1.0, 2.5, 4.0, 50.0, -1000.0]
+    df_kde = sample_df.group_by("id", maintain_order=True).agg(
+        density=pkde.kde(pl.col("a"), eval_points=eval_points, method=method),
+        log=pkde.kde(pl.col("a"), eval_points=eval_points, method=method, log=True),
+        exact=pkde.kde(pl.col("a"), eval_points=eval_points, log=True),
+    )
+
+    assert df_kde["log"].dtype == pl.Array(pl.Float32, 5)
+    rows = zip(
+        df_kde["density"].to_list(), df_kde["log"].to_list(), df_kde["exact"].to_list()
+    )
+    for density, log, exact in rows:
+        # far from the samples, the density underflows but the log-density is finite
+        assert density[3:] == [0.0, 0.0]
+        assert all(math.isfinite(x) for x in log)
+        assert log[:3] == pytest.approx([math.log(d) for d in density[:3]], rel=1e-4)
+        assert log == pytest.approx(exact, rel=1e-4, abs=1e-3)
+
+
+def test_log_density_output(sample_df, eval_points):
+    df = sample_df.group_by("id", maintain_order=True).agg(pl.col("a"))
+    df_kde = df.with_columns(e=pl.lit(eval_points)).select(
+        static=pkde.kde_static_evals(
+            pl.col("a"), eval_points=eval_points, log=True, output="points"
+        ),
+        dynamic=pkde.kde_dynamic_evals(pl.col("a"), pl.col("e"), log=True),
+    )
+
+    assert df_kde["static"].dtype == pl.List(
+        pl.Struct({"x": pl.Float32, "log_density": pl.Float32})
+    )
+    assert_series_equal(
+        df_kde["static"].list.eval(pl.element().struct.field("log_density")),
+        df_kde["dynamic"],
+        check_names=False,
+    )